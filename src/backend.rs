@@ -0,0 +1,659 @@
+//! The linear programs built by [`Solver`](crate::solver::Solver), and the
+//! backends that solve them.
+//!
+//! Each backend needs a cargo feature. By default, `microlp` solves in pure
+//! Rust, and `lp-solvers` hands the problem to CBC or GLPK binaries, which
+//! have to be installed separately. CBC is used by default whenever it is.
+//! `highs` and `coin_cbc` build HiGHS and CBC into the crate instead.
+#[cfg(feature = "microlp")]
+use std::cmp::Ordering;
+#[cfg(feature = "microlp")]
+use std::collections::BinaryHeap;
+use std::collections::HashMap;
+
+use good_lp::{
+    variable, Expression, IntoAffineExpression, ProblemVariables, Variable, VariableDefinition,
+};
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::error::SolverError;
+
+/// The engine a [`Solver`](crate::solver::Solver) solves its linear programs
+/// with.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum SolverBackend {
+    /// microlp, a pure Rust solver. Needs the `microlp` feature, but nothing
+    /// installed. Whole machine counts are found by a simple branch and
+    /// bound, which is slow for large models and gives up after
+    /// [`BRANCH_AND_BOUND_NODE_LIMIT`] branches.
+    Microlp,
+    /// The `cbc` command-line solver. Needs the `lp-solvers` feature and CBC
+    /// installed.
+    Cbc,
+    /// The `glpsol` command-line solver. Needs the `lp-solvers` feature and
+    /// GLPK installed.
+    Glpk,
+    /// HiGHS, built into the crate. Needs the `highs` feature.
+    Highs,
+    /// CBC linked into the crate, rather than run as a separate program.
+    /// Needs the `coin_cbc` feature and the Cbc library installed.
+    CoinCbc,
+}
+
+impl Default for SolverBackend {
+    /// CBC when it's installed, since it finds whole machine counts far
+    /// faster, or else microlp when it's enabled, since it works out of the
+    /// box. CBC is the last resort.
+    fn default() -> Self {
+        if cfg!(feature = "lp-solvers") && installed("cbc") || !cfg!(feature = "microlp") {
+            SolverBackend::Cbc
+        } else {
+            SolverBackend::Microlp
+        }
+    }
+}
+
+/// Whether `program` can be found on the `PATH`.
+fn installed(program: &str) -> bool {
+    std::env::var_os("PATH")
+        .is_some_and(|path| std::env::split_paths(&path).any(|dir| dir.join(program).is_file()))
+}
+
+/// Values of integer variables within this much of a whole number count as
+/// whole.
+#[cfg(feature = "microlp")]
+const INTEGER_TOLERANCE: f64 = 1e-6;
+
+/// How many branches microlp may explore looking for whole machine counts
+/// before giving up.
+pub const BRANCH_AND_BOUND_NODE_LIMIT: usize = 10_000;
+
+/// How the left-hand side of a constraint compares to its right-hand side.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Comparison {
+    LessOrEqual,
+    Equal,
+    GreaterOrEqual,
+}
+
+/// How a variable was defined, kept alongside the variable itself since
+/// `good_lp` doesn't expose definitions once added.
+#[derive(Clone, Debug)]
+struct Definition {
+    name: String,
+    min: f64,
+    max: f64,
+    integer: bool,
+}
+
+impl Definition {
+    fn to_variable(&self) -> VariableDefinition {
+        let definition = variable().min(self.min).max(self.max).name(&self.name);
+        match self.integer {
+            true => definition.integer(),
+            false => definition,
+        }
+    }
+}
+
+/// A linear program to minimise, built independently of the backend that
+/// ends up solving it, and which can be solved more than once.
+pub(crate) struct Problem {
+    /// Hands out the variables of the problem. Every solve adds the same
+    /// definitions in the same order to a fresh set, which hands out the
+    /// very same variables.
+    variables: ProblemVariables,
+    definitions: Vec<(Variable, Definition)>,
+    constraints: Vec<(Expression, Comparison, f64)>,
+}
+
+impl Problem {
+    pub(crate) fn new() -> Self {
+        Self {
+            variables: ProblemVariables::new(),
+            definitions: Vec::new(),
+            constraints: Vec::new(),
+        }
+    }
+
+    /// Adds a variable bounded by `min <= v <= max`, either of which may be
+    /// infinite.
+    pub(crate) fn add_variable(
+        &mut self,
+        name: String,
+        min: f64,
+        max: f64,
+        integer: bool,
+    ) -> Variable {
+        let definition = Definition {
+            name,
+            min,
+            max,
+            integer,
+        };
+        let v = self.variables.add(definition.to_variable());
+        self.definitions.push((v, definition));
+        v
+    }
+
+    /// Adds the constraint `expression <comparison> rhs`, returning its index
+    /// among the constraints of the problem.
+    pub(crate) fn add_constraint(
+        &mut self,
+        expression: Expression,
+        comparison: Comparison,
+        rhs: f64,
+    ) -> usize {
+        self.constraints.push((expression, comparison, rhs));
+        self.constraints.len() - 1
+    }
+
+    /// Replaces the right-hand side of the constraint at index `row`.
+    pub(crate) fn set_rhs(&mut self, row: usize, rhs: f64) {
+        self.constraints[row].2 = rhs;
+    }
+
+    /// Minimises `objective` with `backend`, returning the value of every
+    /// variable at the optimum.
+    pub(crate) fn minimise(
+        &self,
+        objective: &Expression,
+        backend: SolverBackend,
+    ) -> Result<HashMap<Variable, f64>, SolverError> {
+        let rhs = self.constraints.iter().map(|(_, _, rhs)| *rhs);
+        self.minimise_with_rhs(objective, backend, &rhs.collect::<Vec<_>>())
+    }
+
+    /// The dual value of every constraint, in the order they were added: how
+    /// much the minimum of `objective` grows per unit the right-hand side of
+    /// the constraint is raised by. Integer variables are treated as
+    /// continuous.
+    ///
+    /// They're found by solving the dual problem with `backend`. With every
+    /// row i of the problem as `a_i x <comparison> b_i`, and every variable j
+    /// as `l_j <= x_j <= u_j` with cost `c_j` in `objective`, the dual is to
+    /// minimise `-sum(b_i y_i) - sum(l_j v_j) + sum(u_j w_j)` subject to
+    /// `sum(a_ij y_i) + v_j - w_j = c_j` for every variable j, where
+    /// `v, w >= 0` exist only for finite bounds, and y_i is at least 0 for
+    /// `>=` rows, at most 0 for `<=` rows, and free for `=` rows. As not
+    /// every backend reads negative or free variables, each y_i is split into
+    /// a non-negative part for raising the row and one for lowering it.
+    pub(crate) fn dual_values(
+        &self,
+        objective: &Expression,
+        backend: SolverBackend,
+    ) -> Result<Vec<f64>, SolverError> {
+        let mut dual = Problem::new();
+        let mut dual_objective = Expression::from(0.0);
+        let mut columns = self
+            .definitions
+            .iter()
+            .map(|(v, _)| (*v, Expression::from(0.0)))
+            .collect::<HashMap<_, _>>();
+        let mut parts = Vec::new();
+        for (i, (expression, comparison, rhs)) in self.constraints.iter().enumerate() {
+            let b = rhs - expression.constant();
+            let mut part = |name: &str, sign: f64| {
+                let y = dual.add_variable(format!("{}{}", name, i), 0.0, f64::INFINITY, false);
+                dual_objective -= sign * b * y;
+                for (v, coefficient) in expression.linear_coefficients() {
+                    *columns.get_mut(&v).unwrap() += sign * coefficient * y;
+                }
+                y
+            };
+            let raise = (*comparison != Comparison::LessOrEqual).then(|| part("raise", 1.0));
+            let lower = (*comparison != Comparison::GreaterOrEqual).then(|| part("lower", -1.0));
+            parts.push((raise, lower));
+        }
+        for (j, (v, definition)) in self.definitions.iter().enumerate() {
+            let column = columns.get_mut(v).unwrap();
+            if definition.min.is_finite() {
+                let bound = dual.add_variable(format!("min{}", j), 0.0, f64::INFINITY, false);
+                dual_objective -= definition.min * bound;
+                *column += bound;
+            }
+            if definition.max.is_finite() {
+                let bound = dual.add_variable(format!("max{}", j), 0.0, f64::INFINITY, false);
+                dual_objective += definition.max * bound;
+                *column -= bound;
+            }
+        }
+        let cost = |v: &Variable| {
+            objective
+                .linear_coefficients()
+                .find(|(w, _)| w == v)
+                .map_or(0.0, |(_, cost)| cost)
+        };
+        for (v, _) in &self.definitions {
+            let column = columns.remove(v).unwrap();
+            dual.add_constraint(column, Comparison::Equal, cost(v));
+        }
+
+        let values = dual.minimise(&dual_objective, backend)?;
+        let value = |y: Option<Variable>| y.map_or(0.0, |y| values[&y]);
+        Ok(parts
+            .into_iter()
+            .map(|(raise, lower)| value(raise) - value(lower))
+            .collect())
+    }
+
+    /// The reduced cost of `variable`, given the `dual_values` of every
+    /// constraint: how much the minimum of `objective` grows per unit
+    /// `variable` is forced up by.
+    pub(crate) fn reduced_cost(
+        &self,
+        objective: &Expression,
+        dual_values: &[f64],
+        variable: Variable,
+    ) -> f64 {
+        let coefficient = |expression: &Expression| {
+            expression
+                .linear_coefficients()
+                .find(|(v, _)| *v == variable)
+                .map_or(0.0, |(_, coefficient)| coefficient)
+        };
+        let priced = self
+            .constraints
+            .iter()
+            .zip(dual_values)
+            .map(|((expression, _, _), dual)| coefficient(expression) * dual)
+            .sum::<f64>();
+        coefficient(objective) - priced
+    }
+
+    /// Minimises `objective` with `backend`, with the right-hand side of
+    /// every constraint replaced by the one at the same index of `rhs`.
+    fn minimise_with_rhs(
+        &self,
+        objective: &Expression,
+        backend: SolverBackend,
+        rhs: &[f64],
+    ) -> Result<HashMap<Variable, f64>, SolverError> {
+        match backend {
+            SolverBackend::Microlp => self.minimise_with_microlp(objective, rhs),
+            SolverBackend::Cbc => self.minimise_with_lp_solvers(objective, backend, rhs),
+            SolverBackend::Glpk => self.minimise_with_lp_solvers(objective, backend, rhs),
+            SolverBackend::Highs => self.minimise_with_highs(objective, rhs),
+            SolverBackend::CoinCbc => self.minimise_with_coin_cbc(objective, rhs),
+        }
+    }
+
+    /// Minimises `objective` with microlp.
+    ///
+    /// microlp's own branch and bound can settle for a worse plan than the
+    /// best one, so integer variables are branched on here instead, and
+    /// microlp only ever solves linear relaxations.
+    ///
+    /// This is a plain depth-first search, without the cuts and heuristics of
+    /// CBC, so large models with many machine counts to make whole can be
+    /// slow. After [`BRANCH_AND_BOUND_NODE_LIMIT`] branches it gives up with
+    /// [`SolverError::Backend`]; solving in continuous or rounded-up mode, or
+    /// with CBC, avoids branching altogether.
+    #[cfg(feature = "microlp")]
+    fn minimise_with_microlp(
+        &self,
+        objective: &Expression,
+        rhs: &[f64],
+    ) -> Result<HashMap<Variable, f64>, SolverError> {
+        use microlp::{ComparisonOp, OptimizationDirection};
+
+        let index = self
+            .definitions
+            .iter()
+            .enumerate()
+            .map(|(i, (v, _))| (*v, i))
+            .collect::<HashMap<_, _>>();
+        let costs = objective
+            .linear_coefficients()
+            .map(|(v, cost)| (index[&v], cost))
+            .collect::<HashMap<_, _>>();
+        let rows = self
+            .constraints
+            .iter()
+            .zip(rhs)
+            .map(|((expression, comparison, _), rhs)| {
+                let lhs = expression
+                    .linear_coefficients()
+                    .map(|(v, coefficient)| (index[&v], coefficient))
+                    .collect::<Vec<_>>();
+                let op = match comparison {
+                    Comparison::LessOrEqual => ComparisonOp::Le,
+                    Comparison::Equal => ComparisonOp::Eq,
+                    Comparison::GreaterOrEqual => ComparisonOp::Ge,
+                };
+                (lhs, op, rhs - expression.constant())
+            })
+            .collect::<Vec<_>>();
+
+        // Integrality is left to the branch and bound below, so microlp only
+        // ever solves the relaxation.
+        let mut problem = microlp::Problem::new(OptimizationDirection::Minimize);
+        let vars = self
+            .definitions
+            .iter()
+            .enumerate()
+            .map(|(i, (_, d))| {
+                problem.add_var(costs.get(&i).copied().unwrap_or(0.0), (d.min, d.max))
+            })
+            .collect::<Vec<_>>();
+        for (lhs, op, rhs) in rows {
+            let lhs = lhs
+                .into_iter()
+                .map(|(i, coefficient)| (vars[i], coefficient));
+            problem.add_constraint(lhs.collect::<Vec<_>>(), op, rhs);
+        }
+
+        // A whole objective can only be improved on by at least 1, so when
+        // every cost is a whole number on a whole variable, relaxations are
+        // rounded up to compare with the best plan found.
+        let integral = costs
+            .iter()
+            .all(|(i, cost)| self.definitions[*i].1.integer && cost.fract() == 0.0);
+        let mut search = BranchAndBound {
+            integer: self.definitions.iter().map(|(_, d)| d.integer).collect(),
+            vars: &vars,
+            integral,
+            best: None,
+            explored: 0,
+        };
+        search.run(problem.solve()?)?;
+        let best = search.best;
+
+        let (_, values) = best.ok_or(SolverError::Infeasible)?;
+        Ok(self
+            .definitions
+            .iter()
+            .zip(values)
+            .map(|((v, definition), x)| (*v, if definition.integer { x.round() } else { x }))
+            .collect())
+    }
+
+    #[cfg(not(feature = "microlp"))]
+    fn minimise_with_microlp(
+        &self,
+        _objective: &Expression,
+        _rhs: &[f64],
+    ) -> Result<HashMap<Variable, f64>, SolverError> {
+        Err(disabled(SolverBackend::Microlp, "microlp"))
+    }
+
+    #[cfg(feature = "lp-solvers")]
+    fn minimise_with_lp_solvers(
+        &self,
+        objective: &Expression,
+        backend: SolverBackend,
+        rhs: &[f64],
+    ) -> Result<HashMap<Variable, f64>, SolverError> {
+        use good_lp::solvers::lp_solvers::{CbcSolver, GlpkSolver, LpSolver};
+
+        match backend {
+            SolverBackend::Glpk => self.minimise_using(objective, LpSolver(GlpkSolver::new()), rhs),
+            _ => self.minimise_using(objective, LpSolver(CbcSolver::new()), rhs),
+        }
+    }
+
+    #[cfg(not(feature = "lp-solvers"))]
+    fn minimise_with_lp_solvers(
+        &self,
+        _objective: &Expression,
+        backend: SolverBackend,
+        _rhs: &[f64],
+    ) -> Result<HashMap<Variable, f64>, SolverError> {
+        Err(disabled(backend, "lp-solvers"))
+    }
+
+    #[cfg(feature = "highs")]
+    fn minimise_with_highs(
+        &self,
+        objective: &Expression,
+        rhs: &[f64],
+    ) -> Result<HashMap<Variable, f64>, SolverError> {
+        self.minimise_using(objective, good_lp::highs, rhs)
+    }
+
+    #[cfg(not(feature = "highs"))]
+    fn minimise_with_highs(
+        &self,
+        _objective: &Expression,
+        _rhs: &[f64],
+    ) -> Result<HashMap<Variable, f64>, SolverError> {
+        Err(disabled(SolverBackend::Highs, "highs"))
+    }
+
+    #[cfg(feature = "coin_cbc")]
+    fn minimise_with_coin_cbc(
+        &self,
+        objective: &Expression,
+        rhs: &[f64],
+    ) -> Result<HashMap<Variable, f64>, SolverError> {
+        self.minimise_using(objective, good_lp::coin_cbc, rhs)
+    }
+
+    #[cfg(not(feature = "coin_cbc"))]
+    fn minimise_with_coin_cbc(
+        &self,
+        _objective: &Expression,
+        _rhs: &[f64],
+    ) -> Result<HashMap<Variable, f64>, SolverError> {
+        Err(disabled(SolverBackend::CoinCbc, "coin_cbc"))
+    }
+
+    /// Minimises `objective` with one of good_lp's own solvers.
+    #[cfg(any(feature = "lp-solvers", feature = "highs", feature = "coin_cbc"))]
+    fn minimise_using<S>(
+        &self,
+        objective: &Expression,
+        solver: S,
+        rhs: &[f64],
+    ) -> Result<HashMap<Variable, f64>, SolverError>
+    where
+        S: good_lp::Solver,
+        S::Model: good_lp::SolverModel<Error = good_lp::ResolutionError>,
+    {
+        use good_lp::{constraint, Solution, SolverModel};
+
+        let mut variables = ProblemVariables::new();
+        for (v, definition) in &self.definitions {
+            let added = variables.add(definition.to_variable());
+            debug_assert_eq!(added, *v);
+        }
+        let mut problem = variables.minimise(objective).using(solver);
+        for ((expression, comparison, _), rhs) in self.constraints.iter().zip(rhs) {
+            let expression = expression.clone();
+            problem.add_constraint(match comparison {
+                Comparison::LessOrEqual => constraint!(expression <= *rhs),
+                Comparison::Equal => constraint!(expression == *rhs),
+                Comparison::GreaterOrEqual => constraint!(expression >= *rhs),
+            });
+        }
+        let solution = problem.solve()?;
+        Ok(self
+            .definitions
+            .iter()
+            .map(|(v, _)| (*v, solution.value(*v)))
+            .collect())
+    }
+}
+
+/// The search for whole machine counts of [`Problem::minimise_with_microlp`].
+///
+/// Relaxations are explored best bound first, after diving from the first one
+/// by rounding up, which finds a good plan early on: with more machines than
+/// needed, the surplus only leaves the factory. Each branch only adds a bound
+/// to the relaxation of its parent, which microlp re-solves from where the
+/// parent left off.
+#[cfg(feature = "microlp")]
+struct BranchAndBound<'a> {
+    /// Whether each variable has to be whole.
+    integer: Vec<bool>,
+    vars: &'a [microlp::Variable],
+    /// Whether every whole solution has a whole objective.
+    integral: bool,
+    /// The objective and values of the best whole solution found so far.
+    best: Option<(f64, Vec<f64>)>,
+    explored: usize,
+}
+
+#[cfg(feature = "microlp")]
+impl BranchAndBound<'_> {
+    fn run(&mut self, root: microlp::Solution) -> Result<(), SolverError> {
+        self.dive(root.clone())?;
+        let mut nodes = BinaryHeap::from([Node::new(self.bound(&root), root)]);
+        while let Some(Node { bound, relaxed }) = nodes.pop() {
+            // Every relaxation left is bounded at least as high.
+            if self.pruned(bound) {
+                break;
+            }
+            let Some((i, x)) = self.fractional(&relaxed) else {
+                self.record(&relaxed);
+                continue;
+            };
+            for (op, bound) in [
+                (microlp::ComparisonOp::Le, x.floor()),
+                (microlp::ComparisonOp::Ge, x.ceil()),
+            ] {
+                if let Some(branch) = self.branch(&relaxed, i, op, bound)? {
+                    let bound = self.bound(&branch);
+                    if !self.pruned(bound) {
+                        nodes.push(Node::new(bound, branch));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Rounds the variables of `relaxed` up one at a time until they're all
+    /// whole, keeping the plan if it's the best so far.
+    fn dive(&mut self, mut relaxed: microlp::Solution) -> Result<(), SolverError> {
+        while let Some((i, x)) = self.fractional(&relaxed) {
+            if self.pruned(self.bound(&relaxed)) {
+                return Ok(());
+            }
+            match self.branch(&relaxed, i, microlp::ComparisonOp::Ge, x.ceil())? {
+                Some(branch) => relaxed = branch,
+                None => return Ok(()),
+            }
+        }
+        self.record(&relaxed);
+        Ok(())
+    }
+
+    /// The relaxation of `relaxed` with `vars[i] <op> bound` added, or `None`
+    /// if there is none.
+    fn branch(
+        &mut self,
+        relaxed: &microlp::Solution,
+        i: usize,
+        op: microlp::ComparisonOp,
+        bound: f64,
+    ) -> Result<Option<microlp::Solution>, SolverError> {
+        self.explored += 1;
+        if self.explored > BRANCH_AND_BOUND_NODE_LIMIT {
+            return Err(SolverError::Backend(format!(
+                "gave up on finding whole machine counts after {} branches",
+                BRANCH_AND_BOUND_NODE_LIMIT
+            )));
+        }
+        match relaxed
+            .clone()
+            .add_constraint([(self.vars[i], 1.0)], op, bound)
+        {
+            Ok(branch) => Ok(Some(branch)),
+            Err(microlp::Error::Infeasible) => Ok(None),
+            Err(e) => Err(SolverError::from(e)),
+        }
+    }
+
+    /// The lowest objective of any whole solution within `relaxed`.
+    fn bound(&self, relaxed: &microlp::Solution) -> f64 {
+        let optimum = relaxed.objective();
+        match self.integral {
+            true => (optimum - INTEGER_TOLERANCE).ceil(),
+            false => optimum,
+        }
+    }
+
+    /// Whether no solution bounded by `bound` can beat the best one so far.
+    fn pruned(&self, bound: f64) -> bool {
+        self.best
+            .as_ref()
+            .is_some_and(|(best, _)| bound >= best - INTEGER_TOLERANCE * best.abs().max(1.0))
+    }
+
+    /// The whole variable of `relaxed` furthest from a whole number, along
+    /// with its value, if there is one.
+    fn fractional(&self, relaxed: &microlp::Solution) -> Option<(usize, f64)> {
+        let distance = |x: f64| (x - x.round()).abs();
+        self.vars
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| self.integer[*i])
+            .map(|(i, v)| (i, relaxed[*v]))
+            .filter(|(_, x)| distance(*x) > INTEGER_TOLERANCE)
+            .max_by(|(_, a), (_, b)| distance(*a).total_cmp(&distance(*b)))
+    }
+
+    /// Keeps the whole solution `relaxed` if it's the best so far.
+    fn record(&mut self, relaxed: &microlp::Solution) {
+        let optimum = relaxed.objective();
+        if self.best.as_ref().is_none_or(|(best, _)| optimum < *best) {
+            let values = self.vars.iter().map(|v| relaxed[*v]).collect();
+            self.best = Some((optimum, values));
+        }
+    }
+}
+
+/// A relaxation waiting to be branched on, ordered so the lowest bound comes
+/// out of a [`BinaryHeap`] first.
+#[cfg(feature = "microlp")]
+struct Node {
+    bound: f64,
+    relaxed: microlp::Solution,
+}
+
+#[cfg(feature = "microlp")]
+impl Node {
+    fn new(bound: f64, relaxed: microlp::Solution) -> Self {
+        Self { bound, relaxed }
+    }
+}
+
+#[cfg(feature = "microlp")]
+impl PartialEq for Node {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+#[cfg(feature = "microlp")]
+impl Eq for Node {}
+
+#[cfg(feature = "microlp")]
+impl PartialOrd for Node {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[cfg(feature = "microlp")]
+impl Ord for Node {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.bound.total_cmp(&self.bound)
+    }
+}
+
+/// The error for a backend whose feature was left out of the build.
+#[cfg(not(all(
+    feature = "microlp",
+    feature = "lp-solvers",
+    feature = "highs",
+    feature = "coin_cbc"
+)))]
+fn disabled(backend: SolverBackend, feature: &str) -> SolverError {
+    SolverError::Backend(format!(
+        "{:?} needs the crate to be built with the `{}` feature",
+        backend, feature
+    ))
+}
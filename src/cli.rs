@@ -0,0 +1,503 @@
+//! The `factorio_optimizer` command-line interface.
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::io::Write;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use crate::error::SolverError;
+use crate::factorio::Product;
+use crate::graph;
+use crate::model_file;
+use crate::objective::Objective;
+use crate::plan::{ParetoPoint, ProductionPlan};
+use crate::solver::{Model, SolveMode, Solver};
+
+pub const USAGE: &str = "\
+Usage:
+    factorio_optimizer solve <model.json> --target <product=rate>... [--format <text|dot|mermaid>]
+                             [--mode <integer|continuous|rounded>]
+    factorio_optimizer pareto <model.json> --target <product=rate>... --objectives <first,second>
+                              [--points <n>] [--mode <integer|continuous|rounded>]
+    factorio_optimizer validate <model.json>
+    factorio_optimizer list-recipes <model.json>
+    factorio_optimizer list-products <model.json>
+    factorio_optimizer help
+
+Rates are given per second, minute or hour, e.g. `Electronic circuit=120/min`
+or `Iron plate=2/s`. A rate without a unit is per minute. Plans are printed as
+text unless a Graphviz (`dot`) or Mermaid graph is asked for. Model files are
+read as JSON, or as TOML if their name ends in `.toml`.
+
+Machine counts are whole numbers unless solved in `continuous` mode, which
+shows exact ratios, or `rounded` mode, which solves continuously and then
+rounds every group of machines up.
+
+`pareto` prints, as CSV, the plans trading one objective off against another,
+e.g. `--objectives machines,power`. Objectives are `machines`, `power`,
+`pollution` or `area`, and 10 points are tried unless `--points` says
+otherwise.";
+
+/// A subcommand and its arguments.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    /// Solve a model for the given targets and print the plan.
+    Solve {
+        model: PathBuf,
+        targets: Vec<Target>,
+        format: PlanFormat,
+        mode: SolveMode,
+    },
+    /// Print the plans trading one objective off against another as CSV.
+    Pareto {
+        model: PathBuf,
+        targets: Vec<Target>,
+        objectives: (Objective, Objective),
+        points: usize,
+        mode: SolveMode,
+    },
+    /// Print the warnings and errors found in a model.
+    Validate { model: PathBuf },
+    /// Print every recipe in a model.
+    ListRecipes { model: PathBuf },
+    /// Print every product in a model.
+    ListProducts { model: PathBuf },
+    /// Print the usage.
+    Help,
+}
+
+/// How `solve` prints the plan it finds.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum PlanFormat {
+    /// A table of machine groups.
+    #[default]
+    Text,
+    /// A Graphviz graph of the product flows.
+    Dot,
+    /// A Mermaid flowchart of the product flows.
+    Mermaid,
+}
+
+impl FromStr for PlanFormat {
+    type Err = UsageError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(PlanFormat::Text),
+            "dot" => Ok(PlanFormat::Dot),
+            "mermaid" => Ok(PlanFormat::Mermaid),
+            _ => Err(UsageError(format!("unknown format `{}`", s))),
+        }
+    }
+}
+
+impl FromStr for SolveMode {
+    type Err = UsageError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "integer" => Ok(SolveMode::Integer),
+            "continuous" => Ok(SolveMode::Continuous),
+            "rounded" => Ok(SolveMode::RoundedUp),
+            _ => Err(UsageError(format!("unknown mode `{}`", s))),
+        }
+    }
+}
+
+/// Parses one of the objectives that need no products, as named in the
+/// usage.
+fn parse_objective(s: &str) -> Result<Objective, UsageError> {
+    match s.trim() {
+        "machines" => Ok(Objective::Machines),
+        "power" => Ok(Objective::Power),
+        "pollution" => Ok(Objective::Pollution),
+        "area" => Ok(Objective::Area),
+        _ => Err(UsageError(format!("unknown objective `{}`", s))),
+    }
+}
+
+/// The name of an objective, as parsed by [`parse_objective`].
+fn objective_name(objective: &Objective) -> String {
+    match objective {
+        Objective::Machines => "machines".to_owned(),
+        Objective::Power => "power".to_owned(),
+        Objective::Pollution => "pollution".to_owned(),
+        Objective::Area => "area".to_owned(),
+        objective => format!("{:?}", objective),
+    }
+}
+
+/// The command line couldn't be understood.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UsageError(String);
+
+impl Display for UsageError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}\n\n{}", self.0, USAGE)
+    }
+}
+
+impl Error for UsageError {}
+
+/// How much of a product should leave the factory, e.g.
+/// `Electronic circuit=120/min`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Target {
+    product: String,
+    /// Rate in items per minute.
+    rate: f64,
+}
+
+impl Target {
+    pub fn new(product: String, rate: f64) -> Self {
+        Self { product, rate }
+    }
+
+    pub fn product(&self) -> &str {
+        &self.product
+    }
+
+    pub fn rate(&self) -> f64 {
+        self.rate
+    }
+}
+
+impl FromStr for Target {
+    type Err = UsageError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || UsageError(format!("invalid target `{}`", s));
+        let (product, rate) = s.rsplit_once('=').ok_or_else(invalid)?;
+        let (amount, per_minute) = match rate.trim().split_once('/') {
+            Some((amount, unit)) => {
+                let per_minute = match unit.trim() {
+                    "s" | "sec" => 60.0,
+                    "m" | "min" => 1.0,
+                    "h" | "hour" => 1.0 / 60.0,
+                    _ => return Err(invalid()),
+                };
+                (amount, per_minute)
+            }
+            None => (rate, 1.0),
+        };
+        let amount = amount.trim().parse::<f64>().map_err(|_| invalid())?;
+        if product.trim().is_empty() || !amount.is_finite() {
+            return Err(invalid());
+        }
+        Ok(Target::new(product.trim().to_owned(), amount * per_minute))
+    }
+}
+
+/// Parses the arguments following the program name.
+pub fn parse_args<I: IntoIterator<Item = String>>(args: I) -> Result<Command, UsageError> {
+    let mut args = args.into_iter();
+    let command = args
+        .next()
+        .ok_or_else(|| UsageError("missing command".to_owned()))?;
+    if matches!(command.as_str(), "help" | "-h" | "--help") {
+        return Ok(Command::Help);
+    }
+
+    let mut model = None;
+    let mut targets = Vec::new();
+    let mut format = None;
+    let mut mode = None;
+    let mut objectives = None;
+    let mut points = None;
+    while let Some(arg) = args.next() {
+        if let Some(target) = arg.strip_prefix("--target=") {
+            targets.push(target.parse()?);
+        } else if arg == "--target" || arg == "-t" {
+            targets.push(next_value(&arg, &mut args)?.parse()?);
+        } else if let Some(value) = arg.strip_prefix("--format=") {
+            format = Some(value.parse()?);
+        } else if arg == "--format" || arg == "-f" {
+            format = Some(next_value(&arg, &mut args)?.parse()?);
+        } else if let Some(value) = arg.strip_prefix("--mode=") {
+            mode = Some(value.parse()?);
+        } else if arg == "--mode" || arg == "-m" {
+            mode = Some(next_value(&arg, &mut args)?.parse()?);
+        } else if let Some(value) = arg.strip_prefix("--objectives=") {
+            objectives = Some(parse_objectives(value)?);
+        } else if arg == "--objectives" || arg == "-o" {
+            objectives = Some(parse_objectives(&next_value(&arg, &mut args)?)?);
+        } else if let Some(value) = arg.strip_prefix("--points=") {
+            points = Some(parse_points(value)?);
+        } else if arg == "--points" || arg == "-n" {
+            points = Some(parse_points(&next_value(&arg, &mut args)?)?);
+        } else if arg.starts_with('-') {
+            return Err(UsageError(format!("unknown option `{}`", arg)));
+        } else if model.is_none() {
+            model = Some(PathBuf::from(arg));
+        } else {
+            return Err(UsageError(format!("unexpected argument `{}`", arg)));
+        }
+    }
+
+    let model = model.ok_or_else(|| UsageError("missing model file".to_owned()))?;
+    let plans = matches!(command.as_str(), "solve" | "pareto");
+    if !plans && (!targets.is_empty() || mode.is_some())
+        || command != "solve" && format.is_some()
+        || command != "pareto" && (objectives.is_some() || points.is_some())
+    {
+        return Err(UsageError(format!("`{}` takes no such option", command)));
+    }
+    match command.as_str() {
+        "solve" | "pareto" if targets.is_empty() => {
+            Err(UsageError("missing `--target`".to_owned()))
+        }
+        "solve" => Ok(Command::Solve {
+            model,
+            targets,
+            format: format.unwrap_or_default(),
+            mode: mode.unwrap_or_default(),
+        }),
+        "pareto" => Ok(Command::Pareto {
+            model,
+            targets,
+            objectives: objectives
+                .ok_or_else(|| UsageError("missing `--objectives`".to_owned()))?,
+            points: points.unwrap_or(10),
+            mode: mode.unwrap_or_default(),
+        }),
+        "validate" => Ok(Command::Validate { model }),
+        "list-recipes" => Ok(Command::ListRecipes { model }),
+        "list-products" => Ok(Command::ListProducts { model }),
+        _ => Err(UsageError(format!("unknown command `{}`", command))),
+    }
+}
+
+/// Parses two objectives separated by a comma, like `machines,power`.
+fn parse_objectives(s: &str) -> Result<(Objective, Objective), UsageError> {
+    let (first, second) = s
+        .split_once(',')
+        .ok_or_else(|| UsageError(format!("expected two objectives, got `{}`", s)))?;
+    Ok((parse_objective(first)?, parse_objective(second)?))
+}
+
+fn parse_points(s: &str) -> Result<usize, UsageError> {
+    match s.trim().parse::<usize>() {
+        Ok(points) if points >= 2 => Ok(points),
+        _ => Err(UsageError(format!("invalid number of points `{}`", s))),
+    }
+}
+
+/// The argument following `option`.
+fn next_value(option: &str, args: &mut impl Iterator<Item = String>) -> Result<String, UsageError> {
+    args.next()
+        .ok_or_else(|| UsageError(format!("missing value for `{}`", option)))
+}
+
+/// Runs `command`, writing its output to `out`.
+pub fn run(command: &Command, out: &mut dyn Write) -> Result<(), Box<dyn Error>> {
+    match command {
+        Command::Solve {
+            model,
+            targets,
+            format,
+            mode,
+        } => solve(model_file::load_model(model)?, targets, *format, *mode, out),
+        Command::Pareto {
+            model,
+            targets,
+            objectives,
+            points,
+            mode,
+        } => {
+            let solver = solver_for(model_file::load_model(model)?, targets, *mode)?;
+            let (first, second) = objectives;
+            let frontier = solver.pareto_frontier(first, second, *points)?;
+            Ok(write_pareto_csv(&frontier, first, second, out)?)
+        }
+        Command::Validate { model } => validate(&model_file::load_model(model)?, out),
+        Command::ListRecipes { model } => list_recipes(&model_file::load_model(model)?, out),
+        Command::ListProducts { model } => list_products(&model_file::load_model(model)?, out),
+        Command::Help => Ok(writeln!(out, "{}", USAGE)?),
+    }
+}
+
+fn solve(
+    model: Model,
+    targets: &[Target],
+    format: PlanFormat,
+    mode: SolveMode,
+    out: &mut dyn Write,
+) -> Result<(), Box<dyn Error>> {
+    let solver = solver_for(model, targets, mode)?;
+    match solver.solve() {
+        Ok(plan) => match format {
+            PlanFormat::Text => Ok(write_plan(&plan, out)?),
+            PlanFormat::Dot => Ok(write!(out, "{}", graph::to_dot(&plan))?),
+            PlanFormat::Mermaid => Ok(write!(out, "{}", graph::to_mermaid(&plan))?),
+        },
+        Err(SolverError::Infeasible) => {
+            let diagnosis = solver.diagnose()?;
+            Err(format!("{}\n{}", SolverError::Infeasible, diagnosis).into())
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// A solver for `model` asked to produce every target.
+fn solver_for(model: Model, targets: &[Target], mode: SolveMode) -> Result<Solver, SolverError> {
+    let mut solver = Solver::new(model);
+    solver.set_mode(mode);
+    for target in targets {
+        solver.add_production_constraint(Product::from_key(&target.product), target.rate)?;
+    }
+    Ok(solver)
+}
+
+fn validate(model: &Model, out: &mut dyn Write) -> Result<(), Box<dyn Error>> {
+    let validation = model.validate();
+    write!(out, "{}", validation)?;
+    if validation.is_valid() {
+        writeln!(out, "The model is valid.")?;
+        Ok(())
+    } else {
+        Err(format!("found {} errors", validation.errors().count()).into())
+    }
+}
+
+fn list_recipes(model: &Model, out: &mut dyn Write) -> Result<(), Box<dyn Error>> {
+    for recipe in model.recipies() {
+        writeln!(
+            out,
+            "{} ({}, {}s): {} -> {}",
+            recipe.name(),
+            recipe.category(),
+            recipe.production_time(),
+            amounts(recipe.usage()),
+            amounts(recipe.production())
+        )?;
+    }
+    Ok(())
+}
+
+/// Lists product amounts sorted by product, like `2 Iron plate, 1 Coal`.
+fn amounts<T: Display>(amounts: &HashMap<Product, T>) -> String {
+    if amounts.is_empty() {
+        return "nothing".to_owned();
+    }
+    let mut amounts = amounts
+        .iter()
+        .map(|(p, amount)| (p.key(), amount))
+        .collect::<Vec<_>>();
+    amounts.sort_by(|a, b| a.0.cmp(&b.0));
+    amounts
+        .iter()
+        .map(|(product, amount)| format!("{} {}", amount, product))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn list_products(model: &Model, out: &mut dyn Write) -> Result<(), Box<dyn Error>> {
+    for product in model.products() {
+        if product.is_fluid() {
+            writeln!(out, "{} (fluid)", product.key())?;
+        } else {
+            writeln!(out, "{}", product.key())?;
+        }
+    }
+    Ok(())
+}
+
+/// Prints `plan` as a table of machine groups followed by the products
+/// supplied to and leaving the factory.
+pub fn write_plan(plan: &ProductionPlan, out: &mut dyn Write) -> std::io::Result<()> {
+    writeln!(out, "Objective: {}", plan.objective())?;
+    writeln!(out, "Machines:")?;
+    for group in plan.groups() {
+        write!(
+            out,
+            "  {} x {} running {}",
+            group.count(),
+            group.machine(),
+            group.recipe()
+        )?;
+        let loadout = group.loadout();
+        let parts = loadout
+            .modules()
+            .iter()
+            .map(|m| m.name().to_owned())
+            .chain(loadout.beacons().iter().map(|b| {
+                let modules = b.modules().iter().map(|m| m.name()).collect::<Vec<_>>();
+                match modules.is_empty() {
+                    true => format!("{} x {}", b.count(), b.beacon().name()),
+                    false => format!(
+                        "{} x {} ({})",
+                        b.count(),
+                        b.beacon().name(),
+                        modules.join(", ")
+                    ),
+                }
+            }))
+            .collect::<Vec<_>>();
+        if !parts.is_empty() {
+            write!(out, " with {}", parts.join(", "))?;
+        }
+        writeln!(out)?;
+    }
+    writeln!(out, "Total machines: {}", plan.total_machines())?;
+    writeln!(out, "Power: {} kW", plan.power())?;
+    writeln!(out, "Pollution: {}/min", plan.pollution())?;
+
+    let mut rates = |title: &str, rates: Vec<(&String, &f64)>| -> std::io::Result<()> {
+        let mut rates = rates
+            .into_iter()
+            .filter(|(_, rate)| **rate > 0.0)
+            .collect::<Vec<_>>();
+        if rates.is_empty() {
+            return Ok(());
+        }
+        rates.sort_by(|a, b| a.0.cmp(b.0));
+        writeln!(out, "{}:", title)?;
+        for (product, rate) in rates {
+            writeln!(out, "  {}: {}/min", product, rate)?;
+        }
+        Ok(())
+    };
+    rates("Supplied", plan.supply().iter().collect())?;
+    rates("Output", plan.overflow().iter().collect())
+}
+
+/// Prints the plans of a Pareto frontier as CSV: one row per plan with the
+/// values of both objectives and the machines it uses.
+pub fn write_pareto_csv(
+    frontier: &[ParetoPoint],
+    first: &Objective,
+    second: &Objective,
+    out: &mut dyn Write,
+) -> std::io::Result<()> {
+    let escape = |field: String| {
+        if field.contains([',', '"', '\n']) {
+            format!("\"{}\"", field.replace('"', "\"\""))
+        } else {
+            field
+        }
+    };
+    writeln!(
+        out,
+        "{},{},machines",
+        escape(objective_name(first)),
+        escape(objective_name(second))
+    )?;
+    for point in frontier {
+        let machines = point
+            .plan()
+            .groups()
+            .iter()
+            .map(|g| format!("{} x {} running {}", g.count(), g.machine(), g.recipe()))
+            .collect::<Vec<_>>()
+            .join("; ");
+        writeln!(
+            out,
+            "{},{},{}",
+            point.first(),
+            point.second(),
+            escape(machines)
+        )?;
+    }
+    Ok(())
+}
@@ -0,0 +1,236 @@
+//! Explanations of why a model has no feasible plan.
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+
+use crate::factorio::Product;
+use crate::solver::Model;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Why a product can't be produced.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum Reason {
+    /// No recipe yields the product, and none of it is supplied.
+    NoRecipe,
+    /// The recipes yielding the product can't be run by any machine.
+    NoMachine(Vec<String>),
+    /// Every recipe yielding the product needs one of these products, none of
+    /// which can be produced either.
+    MissingInputs(Vec<String>),
+}
+
+/// A product the factory needs but has no way to make.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct UnproducibleProduct {
+    product: String,
+    reason: Reason,
+}
+
+impl UnproducibleProduct {
+    pub fn product(&self) -> &str {
+        &self.product
+    }
+
+    pub fn reason(&self) -> &Reason {
+        &self.reason
+    }
+}
+
+/// One of the constraints the solver builds for a model.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum ConflictingConstraint {
+    /// Everything consumed of the product must be produced or supplied.
+    Balance(String),
+    /// At least this much of the product must leave the factory per minute.
+    MinimumOutput { product: String, amount: f64 },
+    /// At most this much of the product may leave the factory per minute.
+    MaximumOutput { product: String, amount: f64 },
+    /// At most this much of the product may be supplied per minute.
+    SupplyLimit { product: String, amount: f64 },
+    /// At most this much of the fluid may flow through the pipes per minute.
+    PipeThroughput { product: String, amount: f64 },
+    /// No surplus of the product may leave the factory.
+    DisposalForbidden(String),
+}
+
+impl Display for ConflictingConstraint {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConflictingConstraint::Balance(product) => write!(
+                f,
+                "everything consumed of {} must be produced or supplied",
+                product
+            ),
+            ConflictingConstraint::MinimumOutput { product, amount } => write!(
+                f,
+                "at least {}/min of {} must leave the factory",
+                amount, product
+            ),
+            ConflictingConstraint::MaximumOutput { product, amount } => write!(
+                f,
+                "at most {}/min of {} may leave the factory",
+                amount, product
+            ),
+            ConflictingConstraint::SupplyLimit { product, amount } => {
+                write!(f, "at most {}/min of {} is supplied", amount, product)
+            }
+            ConflictingConstraint::PipeThroughput { product, amount } => {
+                write!(
+                    f,
+                    "at most {}/min of {} flows through pipes",
+                    amount, product
+                )
+            }
+            ConflictingConstraint::DisposalForbidden(product) => {
+                write!(f, "no surplus {} may be disposed of", product)
+            }
+        }
+    }
+}
+
+/// Why a model has no feasible plan.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Diagnosis {
+    /// Products needed by the constraints that nothing can produce.
+    unproducible: Vec<UnproducibleProduct>,
+    /// A minimal set of constraints that can't all hold at once: dropping any
+    /// one of them makes the rest feasible.
+    conflict: Vec<ConflictingConstraint>,
+}
+
+impl Diagnosis {
+    pub fn new(
+        unproducible: Vec<UnproducibleProduct>,
+        conflict: Vec<ConflictingConstraint>,
+    ) -> Self {
+        Self {
+            unproducible,
+            conflict,
+        }
+    }
+
+    pub fn unproducible(&self) -> &[UnproducibleProduct] {
+        &self.unproducible
+    }
+
+    pub fn conflict(&self) -> &[ConflictingConstraint] {
+        &self.conflict
+    }
+
+    /// Whether the model was found to be feasible after all.
+    pub fn is_feasible(&self) -> bool {
+        self.unproducible.is_empty() && self.conflict.is_empty()
+    }
+}
+
+impl Display for Diagnosis {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.is_feasible() {
+            return writeln!(f, "The model is feasible.");
+        }
+        if !self.unproducible.is_empty() {
+            writeln!(f, "These products can't be produced:")?;
+            for p in &self.unproducible {
+                match &p.reason {
+                    Reason::NoRecipe => writeln!(f, "  - {}: no recipe produces it", p.product)?,
+                    Reason::NoMachine(recipes) => writeln!(
+                        f,
+                        "  - {}: no machine can run {}",
+                        p.product,
+                        recipes.join(", ")
+                    )?,
+                    Reason::MissingInputs(inputs) => writeln!(
+                        f,
+                        "  - {}: every recipe producing it needs one of {}",
+                        p.product,
+                        inputs.join(", ")
+                    )?,
+                }
+            }
+        }
+        if !self.conflict.is_empty() {
+            writeln!(f, "These constraints can't all hold at once:")?;
+            for c in &self.conflict {
+                writeln!(f, "  - {}", c)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Finds the products in `targets`, and the inputs they transitively need,
+/// that can't be produced from the `supplied` products.
+///
+/// A product counts as producible once some machine can run a recipe yielding
+/// it whose inputs are all producible, so products only made by cycles that
+/// need seeding, such as Kovarex enrichment, are reported as well.
+pub(crate) fn find_unproducible<'a>(
+    model: &'a Model,
+    supplied: impl IntoIterator<Item = &'a Product>,
+    targets: impl IntoIterator<Item = &'a Product>,
+) -> Vec<UnproducibleProduct> {
+    let recipies = model.recipe_variants();
+    let runnable = recipies
+        .iter()
+        .filter(|r| model.machines().iter().any(|m| m.can_craft(r)))
+        .collect::<Vec<_>>();
+
+    let mut available = supplied.into_iter().collect::<HashSet<_>>();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for recipe in &runnable {
+            if recipe.usage().keys().all(|p| available.contains(p)) {
+                for p in recipe.production().keys() {
+                    changed |= available.insert(p);
+                }
+            }
+        }
+    }
+
+    let mut unproducible = Vec::new();
+    let mut seen = HashSet::new();
+    let mut queue = targets
+        .into_iter()
+        .filter(|p| !available.contains(p))
+        .collect::<Vec<_>>();
+    while let Some(product) = queue.pop() {
+        if !seen.insert(product) {
+            continue;
+        }
+        let producers = recipies
+            .iter()
+            .filter(|r| r.production().contains_key(product))
+            .collect::<Vec<_>>();
+        let usable = producers
+            .iter()
+            .filter(|r| runnable.contains(r))
+            .collect::<Vec<_>>();
+        let reason = if producers.is_empty() {
+            Reason::NoRecipe
+        } else if usable.is_empty() {
+            let mut recipes = producers
+                .iter()
+                .map(|r| r.name().to_owned())
+                .collect::<Vec<_>>();
+            recipes.sort();
+            Reason::NoMachine(recipes)
+        } else {
+            let mut missing = usable
+                .iter()
+                .flat_map(|r| r.usage().keys())
+                .filter(|p| !available.contains(p))
+                .collect::<Vec<_>>();
+            missing.sort_by_key(|p| p.key());
+            missing.dedup();
+            queue.extend(missing.iter().copied());
+            Reason::MissingInputs(missing.iter().map(|p| p.key()).collect())
+        };
+        unproducible.push(UnproducibleProduct {
+            product: product.key(),
+            reason,
+        });
+    }
+    unproducible.sort_by(|a, b| a.product.cmp(&b.product));
+    unproducible
+}
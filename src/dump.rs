@@ -0,0 +1,563 @@
+//! Importer for the `script-output/data-raw-dump.json` file written by
+//! `factorio --dump-data`.
+//!
+//! Recipes are read from the `recipe` prototypes, crafting machines from the
+//! `assembling-machine`, `furnace` and `rocket-silo` prototypes, and mining is
+//! modelled as one recipe per `resource` prototype, run by `mining-drill`
+//! prototypes.
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::error::Error;
+use std::path::Path;
+
+use crate::factorio::{Machine, Output, Product, Recipe, DEFAULT_CATEGORY};
+use crate::solver::Model;
+use serde::Deserialize;
+
+/// Which set of recipes to import; recipes without a distinct expensive
+/// variant are the same under both.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Difficulty {
+    Normal,
+    Expensive,
+}
+
+/// Crafting time of a recipe that doesn't set `energy_required`.
+const DEFAULT_ENERGY_REQUIRED: f64 = 0.5;
+/// Resource category of resources and mining drills that don't set one.
+const DEFAULT_RESOURCE_CATEGORY: &str = "basic-solid";
+/// Mining drills consume a tenth of a resource's `fluid_amount` per mining
+/// operation.
+const FLUID_AMOUNT_DIVISOR: f64 = 10.0;
+
+/// Crafting machines with an electric energy source drain this fraction of
+/// their energy usage unless told otherwise.
+const DEFAULT_DRAIN_FRACTION: f64 = 1.0 / 30.0;
+
+/// The `type` of ingredients and results that are fluids rather than items.
+const FLUID_TYPE: &str = "fluid";
+
+/// Product names paired with how much of each is used or produced.
+type Amounts = Vec<(String, f64)>;
+
+/// Product names paired with how much of each a recipe yields.
+type Outputs = Vec<(String, Output)>;
+
+/// Reads a dump from disk and builds a model out of it.
+pub fn load_model<P: AsRef<Path>>(
+    path: P,
+    difficulty: Difficulty,
+) -> Result<Model, Box<dyn Error>> {
+    let contents = std::fs::read_to_string(path)?;
+    Ok(parse_model(&contents, difficulty)?)
+}
+
+/// Builds a model out of the contents of a dump.
+pub fn parse_model(json: &str, difficulty: Difficulty) -> Result<Model, serde_json::Error> {
+    let dump: RawDump = serde_json::from_str(json)?;
+    Ok(dump.into_model(difficulty))
+}
+
+/// Lua serialises empty tables as `{}` rather than `[]`, so every list in the
+/// dump may show up as either.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum LuaList<T> {
+    List(Vec<T>),
+    Table(HashMap<String, T>),
+}
+
+impl<T> LuaList<T> {
+    fn into_vec(self) -> Vec<T> {
+        match self {
+            LuaList::List(items) => items,
+            LuaList::Table(items) => items.into_values().collect(),
+        }
+    }
+}
+
+impl<T> Default for LuaList<T> {
+    fn default() -> Self {
+        LuaList::List(Vec::new())
+    }
+}
+
+#[derive(Deserialize)]
+struct RawDump {
+    #[serde(default)]
+    recipe: HashMap<String, RawRecipe>,
+    #[serde(default, rename = "assembling-machine")]
+    assembling_machine: HashMap<String, RawCraftingMachine>,
+    #[serde(default)]
+    furnace: HashMap<String, RawCraftingMachine>,
+    #[serde(default, rename = "rocket-silo")]
+    rocket_silo: HashMap<String, RawCraftingMachine>,
+    #[serde(default, rename = "mining-drill")]
+    mining_drill: HashMap<String, RawMiningDrill>,
+    #[serde(default)]
+    resource: HashMap<String, RawResource>,
+}
+
+#[derive(Deserialize)]
+struct RawRecipe {
+    name: String,
+    category: Option<String>,
+    /// Recipes without difficulty variants keep their data at the top level.
+    #[serde(flatten)]
+    data: RawRecipeData,
+    normal: Option<RawVariant>,
+    expensive: Option<RawVariant>,
+}
+
+/// A difficulty variant of a recipe; `false` marks it as unavailable.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawVariant {
+    Data(RawRecipeData),
+    Flag(bool),
+}
+
+#[derive(Deserialize)]
+struct RawRecipeData {
+    #[serde(default)]
+    ingredients: LuaList<RawIngredient>,
+    energy_required: Option<f64>,
+    result: Option<String>,
+    result_count: Option<f64>,
+    results: Option<LuaList<RawResult>>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawIngredient {
+    Short(String, f64),
+    Full {
+        name: String,
+        amount: f64,
+        #[serde(rename = "type")]
+        kind: Option<String>,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawResult {
+    Short(String, f64),
+    Full {
+        name: String,
+        #[serde(rename = "type")]
+        kind: Option<String>,
+        amount: Option<f64>,
+        amount_min: Option<f64>,
+        amount_max: Option<f64>,
+        probability: Option<f64>,
+        catalyst_amount: Option<f64>,
+    },
+}
+
+#[derive(Deserialize)]
+struct RawCraftingMachine {
+    name: String,
+    crafting_speed: f64,
+    #[serde(default)]
+    crafting_categories: LuaList<String>,
+    #[serde(flatten)]
+    entity: RawMachineEntity,
+}
+
+#[derive(Deserialize)]
+struct RawMiningDrill {
+    name: String,
+    mining_speed: f64,
+    #[serde(default)]
+    resource_categories: LuaList<String>,
+    #[serde(flatten)]
+    entity: RawMachineEntity,
+}
+
+#[derive(Deserialize)]
+struct RawEnergySource {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default, deserialize_with = "deserialize_power")]
+    drain: Option<f64>,
+    emissions_per_minute: Option<f64>,
+}
+
+impl RawEnergySource {
+    fn is_electric(&self) -> bool {
+        self.kind == "electric"
+    }
+}
+
+/// Corners of an entity's collision box, relative to its centre.
+#[derive(Deserialize)]
+struct RawBoundingBox((f64, f64), (f64, f64));
+
+impl RawBoundingBox {
+    /// Number of whole tiles the box spans along each axis.
+    fn tiles(&self) -> (u32, u32) {
+        let RawBoundingBox((left, top), (right, bottom)) = self;
+        ((right - left).ceil() as u32, (bottom - top).ceil() as u32)
+    }
+}
+
+impl RawMachineEntity {
+    /// Builds a machine out of these fields and the ones specific to its
+    /// prototype.
+    fn into_machine(
+        self,
+        name: String,
+        speed: f64,
+        categories: Vec<String>,
+        default_drain_fraction: f64,
+    ) -> Machine {
+        let emissions = self
+            .energy_source
+            .as_ref()
+            .and_then(|s| s.emissions_per_minute)
+            .unwrap_or(0.0);
+        let (usage, drain) = electric_power(
+            self.energy_usage,
+            self.energy_source,
+            default_drain_fraction,
+        );
+        let machine = Machine::new(name, speed)
+            .with_crafting_categories(categories)
+            .with_module_slots(self.module_specification.map_or(0, |s| s.module_slots))
+            .with_power(usage, drain)
+            .with_emissions(emissions);
+        match self.collision_box.map(|b| b.tiles()) {
+            Some((width, height)) => machine.with_size(width, height),
+            None => machine,
+        }
+    }
+}
+
+/// Parses a power such as `"150kW"` into kW.
+fn parse_power(power: &str) -> Option<f64> {
+    let split = power.find(|c: char| c.is_ascii_alphabetic())?;
+    let (amount, unit) = power.split_at(split);
+    let scale = match unit {
+        "W" => 1e-3,
+        "kW" => 1.0,
+        "MW" => 1e3,
+        "GW" => 1e6,
+        _ => return None,
+    };
+    amount
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .map(|amount| amount * scale)
+}
+
+fn deserialize_power<'de, D>(deserializer: D) -> Result<Option<f64>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Option::<String>::deserialize(deserializer)?
+        .map(|power| {
+            parse_power(&power)
+                .ok_or_else(|| serde::de::Error::custom(format!("invalid power `{}`", power)))
+        })
+        .transpose()
+}
+
+/// Active power usage and drain in kW of a machine, which is zero unless it
+/// runs on electricity.
+fn electric_power(
+    energy_usage: Option<f64>,
+    energy_source: Option<RawEnergySource>,
+    default_drain_fraction: f64,
+) -> (f64, f64) {
+    match energy_source {
+        Some(source) if source.is_electric() => {
+            let usage = energy_usage.unwrap_or(0.0);
+            (
+                usage,
+                source.drain.unwrap_or(usage * default_drain_fraction),
+            )
+        }
+        _ => (0.0, 0.0),
+    }
+}
+
+/// Fields shared by every kind of machine prototype.
+#[derive(Deserialize)]
+struct RawMachineEntity {
+    module_specification: Option<RawModuleSpecification>,
+    #[serde(default, deserialize_with = "deserialize_power")]
+    energy_usage: Option<f64>,
+    energy_source: Option<RawEnergySource>,
+    collision_box: Option<RawBoundingBox>,
+}
+
+#[derive(Deserialize)]
+struct RawModuleSpecification {
+    #[serde(default)]
+    module_slots: usize,
+}
+
+#[derive(Deserialize)]
+struct RawResource {
+    name: String,
+    category: Option<String>,
+    minable: Option<RawMinable>,
+}
+
+#[derive(Deserialize)]
+struct RawMinable {
+    mining_time: f64,
+    result: Option<String>,
+    count: Option<f64>,
+    results: Option<LuaList<RawResult>>,
+    required_fluid: Option<String>,
+    fluid_amount: Option<f64>,
+}
+
+impl RawIngredient {
+    /// The fluid this ingredient names, if it is one.
+    fn fluid(&self) -> Option<&str> {
+        match self {
+            RawIngredient::Full {
+                name,
+                kind: Some(kind),
+                ..
+            } if kind == FLUID_TYPE => Some(name),
+            _ => None,
+        }
+    }
+
+    fn into_pair(self) -> (String, f64) {
+        match self {
+            RawIngredient::Short(name, amount) => (name, amount),
+            RawIngredient::Full { name, amount, .. } => (name, amount),
+        }
+    }
+}
+
+impl RawResult {
+    /// The fluid this result names, if it is one.
+    fn fluid(&self) -> Option<&str> {
+        match self {
+            RawResult::Full {
+                name,
+                kind: Some(kind),
+                ..
+            } if kind == FLUID_TYPE => Some(name),
+            _ => None,
+        }
+    }
+
+    /// How much is produced per craft.
+    fn into_output(self) -> (String, Output) {
+        match self {
+            RawResult::Short(name, amount) => (name, Output::new(amount)),
+            RawResult::Full {
+                name,
+                amount,
+                amount_min,
+                amount_max,
+                probability,
+                catalyst_amount,
+                ..
+            } => {
+                let amount_min = amount.or(amount_min).unwrap_or(0.0);
+                let amount_max = amount.or(amount_max).unwrap_or(amount_min);
+                let output = Output::ranged(amount_min, amount_max)
+                    .with_probability(probability.unwrap_or(1.0))
+                    .with_catalyst_amount(catalyst_amount.unwrap_or(0.0));
+                (name, output)
+            }
+        }
+    }
+}
+
+impl RawRecipeData {
+    /// Splits this variant into its ingredients, its results, and the names of
+    /// the fluids among them.
+    fn into_pairs(self) -> (Amounts, Outputs, Vec<String>) {
+        let ingredients = self.ingredients.into_vec();
+        let results = self.results.map(LuaList::into_vec);
+        let fluids = ingredients
+            .iter()
+            .filter_map(RawIngredient::fluid)
+            .chain(results.iter().flatten().filter_map(RawResult::fluid))
+            .map(str::to_owned)
+            .collect();
+        let ingredients = ingredients
+            .into_iter()
+            .map(RawIngredient::into_pair)
+            .collect();
+        let results = match (results, self.result) {
+            (Some(results), _) => results.into_iter().map(RawResult::into_output).collect(),
+            (None, Some(result)) => {
+                vec![(result, Output::new(self.result_count.unwrap_or(1.0)))]
+            }
+            (None, None) => Vec::new(),
+        };
+        (ingredients, results, fluids)
+    }
+}
+
+/// A recipe before its products have been resolved.
+struct PendingRecipe {
+    name: String,
+    category: String,
+    production_time: f64,
+    usage: Amounts,
+    production: Outputs,
+    /// Names of the products above that are fluids.
+    fluids: Vec<String>,
+}
+
+impl RawRecipe {
+    fn into_pending(self, difficulty: Difficulty) -> Option<PendingRecipe> {
+        let variant = match difficulty {
+            Difficulty::Normal => self.normal,
+            Difficulty::Expensive => self.expensive.or(self.normal),
+        };
+        let data = match variant {
+            Some(RawVariant::Data(data)) => data,
+            Some(RawVariant::Flag(false)) => return None,
+            Some(RawVariant::Flag(true)) | None => self.data,
+        };
+        let production_time = data.energy_required.unwrap_or(DEFAULT_ENERGY_REQUIRED);
+        let (usage, production, fluids) = data.into_pairs();
+        Some(PendingRecipe {
+            name: self.name,
+            category: self.category.unwrap_or_else(|| DEFAULT_CATEGORY.to_owned()),
+            production_time,
+            usage,
+            production,
+            fluids,
+        })
+    }
+}
+
+impl RawResource {
+    fn into_pending(self) -> Option<PendingRecipe> {
+        let minable = self.minable?;
+        let mut fluids = minable.required_fluid.iter().cloned().collect::<Vec<_>>();
+        let production = match (minable.results, minable.result) {
+            (Some(results), _) => {
+                let results = results.into_vec();
+                fluids.extend(
+                    results
+                        .iter()
+                        .filter_map(RawResult::fluid)
+                        .map(str::to_owned),
+                );
+                results.into_iter().map(RawResult::into_output).collect()
+            }
+            (None, Some(result)) => vec![(result, Output::new(minable.count.unwrap_or(1.0)))],
+            (None, None) => return None,
+        };
+        let usage = minable
+            .required_fluid
+            .map(|fluid| {
+                let amount = minable.fluid_amount.unwrap_or(0.0) / FLUID_AMOUNT_DIVISOR;
+                vec![(fluid, amount)]
+            })
+            .unwrap_or_default();
+        Some(PendingRecipe {
+            name: format!("mining-{}", self.name),
+            category: self
+                .category
+                .unwrap_or_else(|| DEFAULT_RESOURCE_CATEGORY.to_owned()),
+            production_time: minable.mining_time,
+            usage,
+            production,
+            fluids,
+        })
+    }
+}
+
+impl RawDump {
+    fn into_model(self, difficulty: Difficulty) -> Model {
+        let mut pending = self
+            .recipe
+            .into_values()
+            .filter_map(|r| r.into_pending(difficulty))
+            .chain(
+                self.resource
+                    .into_values()
+                    .filter_map(RawResource::into_pending),
+            )
+            .collect::<Vec<_>>();
+        pending.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let fluids = pending
+            .iter()
+            .flat_map(|r| r.fluids.iter().map(String::as_str))
+            .collect::<HashSet<_>>();
+        let products = pending
+            .iter()
+            .flat_map(|r| {
+                let usage = r.usage.iter().map(|(name, _)| name);
+                usage.chain(r.production.iter().map(|(name, _)| name))
+            })
+            .cloned()
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .map(|name| {
+                if fluids.contains(name.as_str()) {
+                    Product::fluid(name)
+                } else {
+                    Product::new(name)
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let to_map = |items: Amounts| {
+            let mut map = HashMap::new();
+            for (name, amount) in items {
+                *map.entry(Product::new(name)).or_insert(0.0) += amount;
+            }
+            map
+        };
+        let recipies = pending
+            .into_iter()
+            .map(|r| {
+                let mut outputs = HashMap::<_, Output>::new();
+                for (name, output) in r.production {
+                    outputs
+                        .entry(Product::new(name))
+                        .and_modify(|o| *o = Output::new(o.expected() + output.expected()))
+                        .or_insert(output);
+                }
+                outputs.into_iter().fold(
+                    Recipe::new(r.name, r.production_time, to_map(r.usage), HashMap::new())
+                        .with_category(r.category),
+                    |recipe, (product, output)| recipe.with_output(product, output),
+                )
+            })
+            .collect();
+
+        let mut machines = self
+            .assembling_machine
+            .into_values()
+            .chain(self.furnace.into_values())
+            .chain(self.rocket_silo.into_values())
+            .map(|m| {
+                m.entity.into_machine(
+                    m.name,
+                    m.crafting_speed,
+                    m.crafting_categories.into_vec(),
+                    DEFAULT_DRAIN_FRACTION,
+                )
+            })
+            .chain(self.mining_drill.into_values().map(|m| {
+                let mut categories = m.resource_categories.into_vec();
+                if categories.is_empty() {
+                    categories.push(DEFAULT_RESOURCE_CATEGORY.to_owned());
+                }
+                m.entity
+                    .into_machine(m.name, m.mining_speed, categories, 0.0)
+            }))
+            .collect::<Vec<_>>();
+        machines.sort_by(|a, b| a.name().cmp(b.name()));
+
+        Model::new(recipies, products, machines)
+    }
+}
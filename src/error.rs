@@ -0,0 +1,81 @@
+use std::fmt::{Display, Formatter};
+
+use good_lp::ResolutionError;
+
+use crate::validation::Issue;
+
+/// Everything that can go wrong while setting up or solving a model.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SolverError {
+    /// No plan satisfies every constraint.
+    Infeasible,
+    /// The objective can be improved without limit, e.g. when maximising
+    /// output without limiting the supply of raw resources.
+    Unbounded,
+    /// A constraint names a product that isn't part of the model.
+    UnknownProduct(String),
+    /// The model failed validation with these errors.
+    InvalidModel(Vec<Issue>),
+    /// A constraint was given an amount it can't be satisfied with, such as
+    /// NaN or a negative supply.
+    InvalidAmount { product: String, amount: f64 },
+    /// A Pareto frontier was asked for with fewer than two points, which
+    /// can't hold the best plan at both objectives.
+    TooFewPoints(usize),
+    /// The solver backend failed for some other reason.
+    Backend(String),
+}
+
+impl Display for SolverError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SolverError::Infeasible => write!(f, "no plan satisfies every constraint"),
+            SolverError::Unbounded => write!(f, "the objective is unbounded"),
+            SolverError::UnknownProduct(product) => {
+                write!(f, "product `{}` is not part of the model", product)
+            }
+            SolverError::InvalidModel(issues) => {
+                write!(f, "the model is invalid")?;
+                for (i, issue) in issues.iter().enumerate() {
+                    write!(f, "{} {}", if i == 0 { ":" } else { ";" }, issue)?;
+                }
+                Ok(())
+            }
+            SolverError::InvalidAmount { product, amount } => {
+                write!(f, "invalid amount {} for product `{}`", amount, product)
+            }
+            SolverError::TooFewPoints(points) => {
+                write!(
+                    f,
+                    "a Pareto frontier needs at least 2 points, not {}",
+                    points
+                )
+            }
+            SolverError::Backend(message) => write!(f, "solver backend failed: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for SolverError {}
+
+impl From<ResolutionError> for SolverError {
+    fn from(error: ResolutionError) -> Self {
+        match error {
+            ResolutionError::Infeasible => SolverError::Infeasible,
+            ResolutionError::Unbounded => SolverError::Unbounded,
+            ResolutionError::Other(message) => SolverError::Backend(message.to_owned()),
+            ResolutionError::Str(message) => SolverError::Backend(message),
+        }
+    }
+}
+
+#[cfg(feature = "microlp")]
+impl From<microlp::Error> for SolverError {
+    fn from(error: microlp::Error) -> Self {
+        match error {
+            microlp::Error::Infeasible => SolverError::Infeasible,
+            microlp::Error::Unbounded => SolverError::Unbounded,
+            microlp::Error::InternalError(message) => SolverError::Backend(message),
+        }
+    }
+}
@@ -1,11 +1,322 @@
-use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+/// The crafting category recipes and machines belong to unless told otherwise.
+pub const DEFAULT_CATEGORY: &str = "crafting";
+
+fn default_category() -> String {
+    DEFAULT_CATEGORY.to_owned()
+}
+
+fn default_categories() -> HashSet<String> {
+    HashSet::from([default_category()])
+}
+
+/// Lowest multiplier a negative bonus may bring speed, consumption or
+/// pollution down to.
+const MIN_MULTIPLIER: f64 = 0.2;
+
+/// Bonuses applied to a machine by its modules and the beacons around it.
+/// Each bonus is a fraction, so `0.5` means +50%.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct Effect {
+    #[serde(default)]
+    speed: f64,
+    #[serde(default)]
+    productivity: f64,
+    #[serde(default)]
+    consumption: f64,
+    #[serde(default)]
+    pollution: f64,
+}
+
+impl Effect {
+    pub fn new(speed: f64, productivity: f64, consumption: f64, pollution: f64) -> Self {
+        Self {
+            speed,
+            productivity,
+            consumption,
+            pollution,
+        }
+    }
+
+    pub fn speed(&self) -> f64 {
+        self.speed
+    }
+
+    pub fn productivity(&self) -> f64 {
+        self.productivity
+    }
+
+    pub fn consumption(&self) -> f64 {
+        self.consumption
+    }
+
+    pub fn pollution(&self) -> f64 {
+        self.pollution
+    }
+
+    /// This effect with every bonus multiplied by `factor`.
+    pub fn scaled(&self, factor: f64) -> Self {
+        Self {
+            speed: self.speed * factor,
+            productivity: self.productivity * factor,
+            consumption: self.consumption * factor,
+            pollution: self.pollution * factor,
+        }
+    }
+
+    pub fn speed_multiplier(&self) -> f64 {
+        (1.0 + self.speed).max(MIN_MULTIPLIER)
+    }
+
+    /// Productivity can't drop below the base output of a recipe.
+    pub fn productivity_multiplier(&self) -> f64 {
+        (1.0 + self.productivity).max(1.0)
+    }
+
+    pub fn consumption_multiplier(&self) -> f64 {
+        (1.0 + self.consumption).max(MIN_MULTIPLIER)
+    }
+
+    pub fn pollution_multiplier(&self) -> f64 {
+        (1.0 + self.pollution).max(MIN_MULTIPLIER)
+    }
+}
+
+impl std::ops::Add for Effect {
+    type Output = Effect;
+
+    fn add(self, other: Effect) -> Effect {
+        Effect {
+            speed: self.speed + other.speed,
+            productivity: self.productivity + other.productivity,
+            consumption: self.consumption + other.consumption,
+            pollution: self.pollution + other.pollution,
+        }
+    }
+}
+
+impl std::iter::Sum for Effect {
+    fn sum<I: Iterator<Item = Effect>>(iter: I) -> Effect {
+        iter.fold(Effect::default(), |acc, e| acc + e)
+    }
+}
+
+/// A module, inserted into a machine or a beacon.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Module {
+    name: String,
+    effect: Effect,
+}
+
+impl Module {
+    pub fn new(name: String, effect: Effect) -> Self {
+        Self { name, effect }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn effect(&self) -> Effect {
+        self.effect
+    }
+}
+
+/// A beacon.  It shares the effects of its modules with nearby machines.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Beacon {
+    name: String,
+    /// Fraction of each module's effect passed on to affected machines.
+    distribution_efficiency: f64,
+    module_slots: usize,
+    /// Electrical power drawn at all times, in kW.
+    #[serde(default)]
+    energy_usage: f64,
+}
+
+impl Beacon {
+    pub fn new(name: String, distribution_efficiency: f64, module_slots: usize) -> Self {
+        Self {
+            name,
+            distribution_efficiency,
+            module_slots,
+            energy_usage: 0.0,
+        }
+    }
+
+    /// Sets the power drawn by the beacon, in kW, whether or not the machines
+    /// it reaches are crafting.
+    pub fn with_power(mut self, energy_usage: f64) -> Self {
+        self.energy_usage = energy_usage;
+        self
+    }
+
+    pub fn energy_usage(&self) -> f64 {
+        self.energy_usage
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn distribution_efficiency(&self) -> f64 {
+        self.distribution_efficiency
+    }
+
+    pub fn module_slots(&self) -> usize {
+        self.module_slots
+    }
+}
+
+/// A number of identical beacons affecting a single machine.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct BeaconSetup {
+    beacon: Beacon,
+    /// How many of these beacons reach the machine.
+    count: usize,
+    /// Modules inserted into each beacon.
+    modules: Vec<Module>,
+    /// How many machines each of these beacons reaches, which share its power.
+    #[serde(default = "default_shared_by")]
+    shared_by: usize,
+}
+
+fn default_shared_by() -> usize {
+    1
+}
+
+impl BeaconSetup {
+    pub fn new(beacon: Beacon, count: usize, modules: Vec<Module>) -> Self {
+        Self {
+            beacon,
+            count,
+            modules,
+            shared_by: default_shared_by(),
+        }
+    }
+
+    /// Sets how many machines each of these beacons reaches.
+    pub fn with_shared_by(mut self, shared_by: usize) -> Self {
+        self.shared_by = shared_by;
+        self
+    }
+
+    pub fn shared_by(&self) -> usize {
+        self.shared_by
+    }
+
+    /// This machine's share of the power drawn by these beacons, in kW. A
+    /// beacon reaching no machines at all is charged to this one in full.
+    pub fn power(&self) -> f64 {
+        self.beacon.energy_usage * self.count as f64 / self.shared_by.max(1) as f64
+    }
+
+    pub fn beacon(&self) -> &Beacon {
+        &self.beacon
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn modules(&self) -> &[Module] {
+        &self.modules
+    }
+
+    /// Effect these beacons have on the machine they reach.
+    pub fn effect(&self) -> Effect {
+        self.modules
+            .iter()
+            .map(Module::effect)
+            .sum::<Effect>()
+            .scaled(self.beacon.distribution_efficiency * self.count as f64)
+    }
+}
+
+/// The modules inserted into a machine and the beacons affecting it.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Loadout {
+    #[serde(default)]
+    modules: Vec<Module>,
+    #[serde(default)]
+    beacons: Vec<BeaconSetup>,
+}
+
+impl Loadout {
+    pub fn new(modules: Vec<Module>, beacons: Vec<BeaconSetup>) -> Self {
+        Self { modules, beacons }
+    }
+
+    pub fn modules(&self) -> &[Module] {
+        &self.modules
+    }
+
+    pub fn beacons(&self) -> &[BeaconSetup] {
+        &self.beacons
+    }
+
+    /// How many modules this loadout uses, counting every module in every
+    /// beacon even though beacons are usually shared between machines.
+    pub fn module_count(&self) -> usize {
+        self.modules.len()
+            + self
+                .beacons
+                .iter()
+                .map(|b| b.count * b.modules.len())
+                .sum::<usize>()
+    }
+
+    /// The share of beacon power charged to a machine using this loadout, in
+    /// kW.
+    pub fn beacon_power(&self) -> f64 {
+        self.beacons.iter().map(BeaconSetup::power).sum()
+    }
+
+    /// Combined effect of every module and beacon in this loadout.
+    pub fn effect(&self) -> Effect {
+        self.modules
+            .iter()
+            .map(Module::effect)
+            .chain(self.beacons.iter().map(BeaconSetup::effect))
+            .sum()
+    }
+}
 
 /// A machine.  It produces materials using a recipe.
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Machine {
     name: String,
     production_rate: f64,
+    /// Categories of recipes this machine is able to craft.
+    #[serde(
+        default = "default_categories",
+        serialize_with = "serialize_categories"
+    )]
+    crafting_categories: HashSet<String>,
+    /// How many modules fit into this machine.
+    #[serde(default)]
+    module_slots: usize,
+    /// Modules and beacons applied to every machine of this type.
+    #[serde(default)]
+    loadout: Loadout,
+    /// Electrical power drawn while crafting, in kW, before module bonuses.
+    #[serde(default)]
+    energy_usage: f64,
+    /// Electrical power drawn at all times, even while idle, in kW.
+    #[serde(default)]
+    drain: f64,
+    /// Pollution emitted per minute while crafting, before module bonuses.
+    #[serde(default)]
+    emissions: f64,
+    /// Footprint of the machine in tiles, as (width, height).
+    #[serde(default = "default_size")]
+    size: (u32, u32),
+}
+
+fn default_size() -> (u32, u32) {
+    (1, 1)
 }
 
 impl Machine {
@@ -13,9 +324,160 @@ impl Machine {
         Self {
             name,
             production_rate,
+            crafting_categories: default_categories(),
+            module_slots: 0,
+            loadout: Loadout::default(),
+            energy_usage: 0.0,
+            drain: 0.0,
+            emissions: 0.0,
+            size: default_size(),
         }
     }
 
+    /// Sets the pollution emitted per minute while crafting.
+    pub fn with_emissions(mut self, emissions: f64) -> Self {
+        self.emissions = emissions;
+        self
+    }
+
+    /// Sets the footprint of this machine in tiles.
+    pub fn with_size(mut self, width: u32, height: u32) -> Self {
+        self.size = (width, height);
+        self
+    }
+
+    pub fn emissions(&self) -> f64 {
+        self.emissions
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        self.size
+    }
+
+    /// Number of tiles this machine covers.
+    pub fn area(&self) -> f64 {
+        f64::from(self.size.0 * self.size.1)
+    }
+
+    /// Pollution emitted per minute by this machine while crafting.
+    pub fn pollution(&self) -> f64 {
+        self.pollution_with(&self.loadout)
+    }
+
+    /// Pollution this machine would emit per minute if it used `loadout`
+    /// instead of its own.  Consumption bonuses raise pollution as well.
+    pub fn pollution_with(&self, loadout: &Loadout) -> f64 {
+        let effect = loadout.effect();
+        self.emissions * effect.consumption_multiplier() * effect.pollution_multiplier()
+    }
+
+    /// Sets the power drawn while crafting and the drain, both in kW.
+    pub fn with_power(mut self, energy_usage: f64, drain: f64) -> Self {
+        self.energy_usage = energy_usage;
+        self.drain = drain;
+        self
+    }
+
+    pub fn energy_usage(&self) -> f64 {
+        self.energy_usage
+    }
+
+    pub fn drain(&self) -> f64 {
+        self.drain
+    }
+
+    /// Power drawn by this machine while idle, in kW: its drain, plus its
+    /// share of the beacons reaching it.
+    pub fn idle_power(&self) -> f64 {
+        self.idle_power_with(&self.loadout)
+    }
+
+    /// Power this machine would draw while idle if it used `loadout` instead
+    /// of its own, in kW.
+    pub fn idle_power_with(&self, loadout: &Loadout) -> f64 {
+        self.drain + loadout.beacon_power()
+    }
+
+    /// Power drawn by this machine while crafting, in kW.
+    pub fn active_power(&self) -> f64 {
+        self.active_power_with(&self.loadout)
+    }
+
+    /// Power this machine would draw while crafting if it used `loadout`
+    /// instead of its own, in kW.
+    pub fn active_power_with(&self, loadout: &Loadout) -> f64 {
+        self.energy_usage * loadout.effect().consumption_multiplier()
+            + self.idle_power_with(loadout)
+    }
+
+    pub fn with_module_slots(mut self, module_slots: usize) -> Self {
+        self.module_slots = module_slots;
+        self
+    }
+
+    /// Sets the modules and beacons applied to this machine.
+    pub fn with_loadout(mut self, loadout: Loadout) -> Self {
+        self.loadout = loadout;
+        self
+    }
+
+    pub fn module_slots(&self) -> usize {
+        self.module_slots
+    }
+
+    pub fn loadout(&self) -> &Loadout {
+        &self.loadout
+    }
+
+    /// Whether `loadout` fits into this machine and the beacons it names.
+    pub fn accepts(&self, loadout: &Loadout) -> bool {
+        loadout.modules.len() <= self.module_slots
+            && loadout
+                .beacons
+                .iter()
+                .all(|b| b.modules.len() <= b.beacon.module_slots)
+    }
+
+    /// Crafting speed of this machine after module and beacon bonuses.
+    pub fn crafting_speed(&self) -> f64 {
+        self.crafting_speed_with(&self.loadout)
+    }
+
+    /// Crafting speed of this machine if it used `loadout` instead of its own.
+    pub fn crafting_speed_with(&self, loadout: &Loadout) -> f64 {
+        self.production_rate * loadout.effect().speed_multiplier()
+    }
+
+    /// Multiplier applied to the output of every recipe this machine runs.
+    pub fn productivity(&self) -> f64 {
+        self.productivity_with(&self.loadout)
+    }
+
+    /// Productivity multiplier of this machine if it used `loadout` instead of
+    /// its own.
+    pub fn productivity_with(&self, loadout: &Loadout) -> f64 {
+        loadout.effect().productivity_multiplier()
+    }
+
+    /// Replaces the crafting categories of this machine.
+    pub fn with_crafting_categories<I, S>(mut self, categories: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.crafting_categories = categories.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn crafting_categories(&self) -> &HashSet<String> {
+        &self.crafting_categories
+    }
+
+    /// Whether this machine is able to run the given recipe.
+    pub fn can_craft(&self, recipe: &Recipe) -> bool {
+        self.crafting_categories.contains(recipe.category())
+    }
+
     pub fn name(&self) -> &str {
         &self.name
     }
@@ -39,31 +501,437 @@ impl PartialEq for Machine {
 
 impl Eq for Machine {}
 
-#[derive(Hash, PartialEq, Eq, Serialize, Deserialize, Clone, Debug)]
+/// Whether a product is carried on belts or through pipes.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ProductKind {
+    #[default]
+    Item,
+    Fluid,
+}
+
+/// Separates a fluid's name from its temperature in product keys.
+const TEMPERATURE_SEPARATOR: char = '@';
+
+/// A product, identified by its name and, for fluids such as steam, its
+/// temperature, so steam at 165°C and steam at 500°C are different products.
+///
+/// Plain items are serialized as just their name; other products as a struct.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(from = "RawProduct", into = "RawProduct")]
 pub struct Product {
     name: String,
+    kind: ProductKind,
+    /// Temperature of a fluid, in °C.
+    temperature: Option<f64>,
 }
 
 impl Product {
     pub fn new(name: String) -> Self {
-        Self { name }
+        Self {
+            name,
+            kind: ProductKind::Item,
+            temperature: None,
+        }
+    }
+
+    /// Creates a fluid of no particular temperature, like water.
+    pub fn fluid(name: String) -> Self {
+        Self {
+            name,
+            kind: ProductKind::Fluid,
+            temperature: None,
+        }
+    }
+
+    /// Sets the temperature of this fluid, in °C.
+    pub fn with_temperature(mut self, temperature: f64) -> Self {
+        self.kind = ProductKind::Fluid;
+        self.temperature = Some(temperature);
+        self
+    }
+
+    /// Parses a key written by [`Product::key`]. Only the kind of fluids with
+    /// a temperature can be told from their key; everything else is assumed
+    /// to be an item.
+    pub fn from_key(key: &str) -> Self {
+        match key.rsplit_once(TEMPERATURE_SEPARATOR) {
+            Some((name, temperature)) => match temperature.parse() {
+                Ok(temperature) => Product::fluid(name.to_owned()).with_temperature(temperature),
+                Err(_) => Product::new(key.to_owned()),
+            },
+            None => Product::new(key.to_owned()),
+        }
     }
 
     pub fn name(&self) -> &str {
         &self.name
     }
+
+    pub fn kind(&self) -> ProductKind {
+        self.kind
+    }
+
+    pub fn is_fluid(&self) -> bool {
+        self.kind == ProductKind::Fluid
+    }
+
+    pub fn temperature(&self) -> Option<f64> {
+        self.temperature
+    }
+
+    /// A string identifying this product: its name, followed by `@` and its
+    /// temperature if it has one, e.g. `Steam@165`.
+    pub fn key(&self) -> String {
+        match self.temperature {
+            Some(temperature) => format!("{}{}{}", self.name, TEMPERATURE_SEPARATOR, temperature),
+            None => self.name.clone(),
+        }
+    }
+}
+
+/// Products are the same if they share a name and temperature, whatever their
+/// kind, so recipes may refer to fluids by key alone.
+impl core::hash::Hash for Product {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.name.hash(state);
+        self.temperature.map(f64::to_bits).hash(state);
+    }
+}
+
+impl PartialEq for Product {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+            && self.temperature.map(f64::to_bits) == other.temperature.map(f64::to_bits)
+    }
+}
+
+impl Eq for Product {}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum RawProduct {
+    Name(String),
+    Full {
+        name: String,
+        #[serde(default)]
+        kind: ProductKind,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        temperature: Option<f64>,
+    },
+}
+
+impl From<RawProduct> for Product {
+    fn from(raw: RawProduct) -> Self {
+        match raw {
+            RawProduct::Name(name) => Product::new(name),
+            RawProduct::Full {
+                name,
+                kind,
+                temperature,
+            } => Product {
+                name,
+                kind: if temperature.is_some() {
+                    ProductKind::Fluid
+                } else {
+                    kind
+                },
+                temperature,
+            },
+        }
+    }
+}
+
+impl From<Product> for RawProduct {
+    fn from(product: Product) -> Self {
+        match (product.kind, product.temperature) {
+            (ProductKind::Item, None) => RawProduct::Name(product.name),
+            (kind, temperature) => RawProduct::Full {
+                name: product.name,
+                kind,
+                temperature,
+            },
+        }
+    }
+}
+
+/// The temperatures of a fluid a recipe accepts, in °C. Either end may be left
+/// open.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct TemperatureRange {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    min: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    max: Option<f64>,
+}
+
+impl TemperatureRange {
+    pub fn new(min: Option<f64>, max: Option<f64>) -> Self {
+        Self { min, max }
+    }
+
+    pub fn min(&self) -> Option<f64> {
+        self.min
+    }
+
+    pub fn max(&self) -> Option<f64> {
+        self.max
+    }
+
+    /// Whether a fluid at `temperature` falls within this range.
+    pub fn contains(&self, temperature: f64) -> bool {
+        self.min.is_none_or(|min| temperature >= min)
+            && self.max.is_none_or(|max| temperature <= max)
+    }
+}
+
+/// How much of a product a single craft of a recipe yields. The amount is
+/// picked uniformly between `amount_min` and `amount_max`, and only yielded
+/// with the given probability, as with uranium-235 from uranium processing.
+///
+/// Part of the amount may be a catalyst: product the recipe also consumes
+/// and merely returns, like the U-235 of Kovarex enrichment, which
+/// productivity bonuses don't apply to.
+///
+/// Fixed amounts are serialized as just a number.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(from = "RawOutput", into = "RawOutput")]
+pub struct Output {
+    amount_min: f64,
+    amount_max: f64,
+    probability: f64,
+    catalyst_amount: f64,
+}
+
+impl Output {
+    /// Yields exactly `amount` every craft.
+    pub fn new(amount: f64) -> Self {
+        Self::ranged(amount, amount)
+    }
+
+    /// Yields anywhere from `amount_min` to `amount_max` every craft.
+    pub fn ranged(amount_min: f64, amount_max: f64) -> Self {
+        Self {
+            amount_min,
+            amount_max,
+            probability: 1.0,
+            catalyst_amount: 0.0,
+        }
+    }
+
+    /// Only yields anything with the given probability, between 0 and 1.
+    pub fn with_probability(mut self, probability: f64) -> Self {
+        self.probability = probability;
+        self
+    }
+
+    /// Marks `catalyst_amount` of each craft's yield as returned catalyst.
+    pub fn with_catalyst_amount(mut self, catalyst_amount: f64) -> Self {
+        self.catalyst_amount = catalyst_amount;
+        self
+    }
+
+    pub fn amount_min(&self) -> f64 {
+        self.amount_min
+    }
+
+    pub fn amount_max(&self) -> f64 {
+        self.amount_max
+    }
+
+    pub fn probability(&self) -> f64 {
+        self.probability
+    }
+
+    pub fn catalyst_amount(&self) -> f64 {
+        self.catalyst_amount
+    }
+
+    /// The average amount yielded per craft, which is what the solver plans
+    /// with.
+    pub fn expected(&self) -> f64 {
+        self.expected_with(1.0)
+    }
+
+    /// The average amount yielded per craft by a machine with the given
+    /// productivity multiplier, which only applies to the amount beyond the
+    /// catalyst.
+    pub fn expected_with(&self, productivity: f64) -> f64 {
+        let amount = (self.amount_min + self.amount_max) / 2.0;
+        let bonus = (productivity - 1.0) * (amount - self.catalyst_amount).max(0.0);
+        (amount + bonus) * self.probability
+    }
+
+    /// Whether the amounts and probability make sense: finite, not negative,
+    /// the range in order, and the probability above 0 and at most 1.
+    pub fn is_valid(&self) -> bool {
+        self.catalyst_amount.is_finite()
+            && 0.0 <= self.catalyst_amount
+            && self.amount_min.is_finite()
+            && self.amount_max.is_finite()
+            && 0.0 <= self.amount_min
+            && self.amount_min <= self.amount_max
+            && 0.0 < self.probability
+            && self.probability <= 1.0
+    }
+}
+
+impl From<f64> for Output {
+    fn from(amount: f64) -> Self {
+        Output::new(amount)
+    }
+}
+
+impl std::fmt::Display for Output {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.amount_min == self.amount_max {
+            write!(f, "{}", self.amount_min)?;
+        } else {
+            write!(f, "{}-{}", self.amount_min, self.amount_max)?;
+        }
+        if self.probability != 1.0 {
+            write!(f, " ({}%)", self.probability * 100.0)?;
+        }
+        if self.catalyst_amount != 0.0 {
+            write!(f, " ({} catalyst)", self.catalyst_amount)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum RawOutput {
+    Amount(f64),
+    Full {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        amount: Option<f64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        amount_min: Option<f64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        amount_max: Option<f64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        probability: Option<f64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        catalyst_amount: Option<f64>,
+    },
+}
+
+impl From<RawOutput> for Output {
+    fn from(raw: RawOutput) -> Self {
+        match raw {
+            RawOutput::Amount(amount) => Output::new(amount),
+            RawOutput::Full {
+                amount,
+                amount_min,
+                amount_max,
+                probability,
+                catalyst_amount,
+            } => {
+                let amount_min = amount.or(amount_min).unwrap_or(0.0);
+                let amount_max = amount.or(amount_max).unwrap_or(amount_min);
+                Output::ranged(amount_min, amount_max)
+                    .with_probability(probability.unwrap_or(1.0))
+                    .with_catalyst_amount(catalyst_amount.unwrap_or(0.0))
+            }
+        }
+    }
+}
+
+impl From<Output> for RawOutput {
+    fn from(output: Output) -> Self {
+        let probability = Some(output.probability).filter(|p| *p != 1.0);
+        let catalyst_amount = Some(output.catalyst_amount).filter(|c| *c != 0.0);
+        let fixed = output.amount_min == output.amount_max;
+        if fixed && probability.is_none() && catalyst_amount.is_none() {
+            return RawOutput::Amount(output.amount_min);
+        }
+        RawOutput::Full {
+            amount: Some(output.amount_min).filter(|_| fixed),
+            amount_min: Some(output.amount_min).filter(|_| !fixed),
+            amount_max: Some(output.amount_max).filter(|_| !fixed),
+            probability,
+            catalyst_amount,
+        }
+    }
+}
+
+/// Writes crafting categories sorted, so saved models diff cleanly.
+fn serialize_categories<S: Serializer>(
+    categories: &HashSet<String>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    categories
+        .iter()
+        .collect::<BTreeSet<_>>()
+        .serialize(serializer)
+}
+
+/// Writes product amounts keyed and sorted by [`Product::key`], so saved
+/// models diff cleanly.
+fn serialize_amounts<S: Serializer, T: Serialize>(
+    amounts: &HashMap<Product, T>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let sorted = amounts
+        .iter()
+        .map(|(p, amount)| (p.key(), amount))
+        .collect::<BTreeMap<_, _>>();
+    sorted.serialize(serializer)
+}
+
+/// Reads product amounts keyed by [`Product::key`].
+fn deserialize_amounts<'de, D: Deserializer<'de>, T: Deserialize<'de>>(
+    deserializer: D,
+) -> Result<HashMap<Product, T>, D::Error> {
+    let amounts = HashMap::<String, T>::deserialize(deserializer)?;
+    Ok(amounts
+        .into_iter()
+        .map(|(key, amount)| (Product::from_key(&key), amount))
+        .collect())
+}
+
+/// Writes temperature ranges sorted by fluid, so saved models diff cleanly.
+fn serialize_temperatures<S: Serializer>(
+    temperatures: &HashMap<String, TemperatureRange>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    temperatures
+        .iter()
+        .collect::<BTreeMap<_, _>>()
+        .serialize(serializer)
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Recipe {
     /// Name of the recipe.
     name: String,
+    /// Crafting category of the recipe; only machines supporting it may run it.
+    #[serde(default = "default_category")]
+    category: String,
     /// Amount of time to produce in seconds.
     production_time: f64,
     /// How much of a product is used/produced in this recipe
+    #[serde(
+        default,
+        serialize_with = "serialize_amounts",
+        deserialize_with = "deserialize_amounts"
+    )]
     usage: HashMap<Product, f64>,
     /// How much of a product is used/produced in this recipe
-    production: HashMap<Product, f64>,
+    #[serde(
+        serialize_with = "serialize_amounts",
+        deserialize_with = "deserialize_amounts"
+    )]
+    production: HashMap<Product, Output>,
+    /// Temperatures of the fluids this recipe uses, by fluid name. A fluid
+    /// with a range is used in its place at any temperature within it.
+    #[serde(
+        default,
+        skip_serializing_if = "HashMap::is_empty",
+        serialize_with = "serialize_temperatures"
+    )]
+    temperatures: HashMap<String, TemperatureRange>,
 }
 
 impl Recipe {
@@ -75,16 +943,117 @@ impl Recipe {
     ) -> Self {
         Self {
             name,
+            category: default_category(),
             production_time,
             usage,
-            production,
+            production: production
+                .into_iter()
+                .map(|(p, amount)| (p, Output::new(amount)))
+                .collect(),
+            temperatures: HashMap::new(),
+        }
+    }
+
+    /// Sets the crafting category of this recipe.
+    pub fn with_category(mut self, category: String) -> Self {
+        self.category = category;
+        self
+    }
+
+    /// Sets how much of `product` this recipe yields, replacing any fixed
+    /// amount it was created with.
+    pub fn with_output(mut self, product: Product, output: Output) -> Self {
+        self.production.insert(product, output);
+        self
+    }
+
+    /// Lets this recipe use `fluid` at any temperature within `range`. The
+    /// fluid should be listed in the recipe's usage without a temperature.
+    pub fn with_temperature_range(mut self, fluid: String, range: TemperatureRange) -> Self {
+        self.temperatures.insert(fluid, range);
+        self
+    }
+
+    pub fn temperature_range(&self, fluid: &str) -> Option<TemperatureRange> {
+        self.temperatures.get(fluid).copied()
+    }
+
+    pub fn temperatures(&self) -> &HashMap<String, TemperatureRange> {
+        &self.temperatures
+    }
+
+    /// Whether this recipe uses `product` in place of an ingredient with a
+    /// temperature range.
+    fn accepts(&self, ingredient: &Product, product: &Product) -> bool {
+        ingredient.name == product.name
+            && ingredient.temperature.is_none()
+            && match (self.temperatures.get(&ingredient.name), product.temperature) {
+                (Some(range), Some(temperature)) => range.contains(temperature),
+                _ => false,
+            }
+    }
+
+    /// Splits this recipe into one recipe for each combination of `products`
+    /// its fluids with a temperature range may be, all sharing its name. A
+    /// recipe without temperature ranges is returned as is, and one with a
+    /// range no product falls within has no variants at all.
+    pub fn variants(&self, products: &[Product]) -> Vec<Recipe> {
+        let mut variants = vec![Recipe {
+            usage: HashMap::new(),
+            temperatures: HashMap::new(),
+            ..self.clone()
+        }];
+        let mut ingredients = self.usage.iter().collect::<Vec<_>>();
+        ingredients.sort_by_key(|(p, _)| p.key());
+        for (ingredient, amount) in ingredients {
+            if !self.temperatures.contains_key(&ingredient.name) || ingredient.temperature.is_some()
+            {
+                for variant in &mut variants {
+                    variant.usage.insert(ingredient.clone(), *amount);
+                }
+                continue;
+            }
+            variants = variants
+                .into_iter()
+                .flat_map(|v| {
+                    products
+                        .iter()
+                        .filter(|p| self.accepts(ingredient, p))
+                        .map(move |p| {
+                            let mut variant = v.clone();
+                            variant.usage.insert(p.clone(), *amount);
+                            variant
+                        })
+                })
+                .collect();
         }
+        variants
+    }
+
+    /// Replaces the products this recipe uses and produces with the same
+    /// products out of `products`, so they carry the kinds listed there.
+    pub(crate) fn resolve_products(&mut self, products: &[Product]) {
+        fn resolve<T>(amounts: &mut HashMap<Product, T>, products: &[Product]) {
+            *amounts = amounts
+                .drain()
+                .map(|(p, amount)| {
+                    let p = products.iter().find(|q| **q == p).cloned().unwrap_or(p);
+                    (p, amount)
+                })
+                .collect();
+        }
+        resolve(&mut self.usage, products);
+        resolve(&mut self.production, products);
     }
 
     pub fn name(&self) -> &str {
         self.name.as_ref()
     }
 
+    pub fn category(&self) -> &str {
+        &self.category
+    }
+
     pub fn production_time(&self) -> f64 {
         self.production_time
     }
@@ -93,8 +1062,25 @@ impl Recipe {
         self.usage.get(product).copied()
     }
 
+    /// The expected amount of `product` a single craft yields.
     pub fn production_of(&self, product: &Product) -> Option<f64> {
-        self.production.get(product).copied()
+        self.production.get(product).map(Output::expected)
+    }
+
+    /// The expected amount of `product` a single craft yields in a machine
+    /// with the given productivity multiplier. See [`Output::expected_with`].
+    pub fn production_with(&self, product: &Product, productivity: f64) -> Option<f64> {
+        self.production
+            .get(product)
+            .map(|o| o.expected_with(productivity))
+    }
+
+    pub fn usage(&self) -> &HashMap<Product, f64> {
+        &self.usage
+    }
+
+    pub fn production(&self) -> &HashMap<Product, Output> {
+        &self.production
     }
 }
 
@@ -0,0 +1,212 @@
+//! Exports a production plan as a graph, for Graphviz or Mermaid.
+//!
+//! Every recipe group becomes a node labelled with its machine count, machine
+//! and recipe, and every product flowing between two nodes becomes an edge
+//! labelled with its rate per minute. Supplied products start at a source
+//! node of their own, and products leaving the factory end at a sink node.
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+use crate::plan::ProductionPlan;
+
+/// Flows below this many items per minute are left out of the graph.
+const MIN_FLOW: f64 = 1e-6;
+
+/// A node of the production graph.
+#[derive(Clone, Debug, PartialEq)]
+enum Node {
+    /// The recipe group at this index of the plan.
+    Group(usize),
+    /// Where a supplied product enters the factory.
+    Supply(String),
+    /// Where a product leaves the factory.
+    Output(String),
+}
+
+/// An amount of a product moving from one node to another, per minute.
+struct Flow<'a> {
+    from: Node,
+    to: Node,
+    product: &'a str,
+    rate: f64,
+}
+
+/// Splits the flow of every product between its producers and consumers.
+///
+/// A plan only says how much of a product each group makes or uses, not
+/// which group feeds which, so every producer is assumed to feed every
+/// consumer in proportion to how much each of them makes and uses.
+fn flows(plan: &ProductionPlan) -> Vec<Flow<'_>> {
+    let products = plan
+        .groups()
+        .iter()
+        .flat_map(|g| g.rates().keys())
+        .chain(plan.supply().keys())
+        .map(String::as_str)
+        .collect::<BTreeSet<_>>();
+
+    let mut flows = Vec::new();
+    for product in products {
+        let mut sources = Vec::new();
+        let mut sinks = Vec::new();
+        for (i, group) in plan.groups().iter().enumerate() {
+            let rate = group.rates().get(product).copied().unwrap_or(0.0);
+            if rate > MIN_FLOW {
+                sources.push((Node::Group(i), rate));
+            } else if rate < -MIN_FLOW {
+                sinks.push((Node::Group(i), -rate));
+            }
+        }
+        let supplied = plan.supply().get(product).copied().unwrap_or(0.0);
+        if supplied > MIN_FLOW {
+            sources.push((Node::Supply(product.to_owned()), supplied));
+        }
+        let overflow = plan.overflow().get(product).copied().unwrap_or(0.0);
+        if overflow > MIN_FLOW {
+            sinks.push((Node::Output(product.to_owned()), overflow));
+        }
+
+        let total = sinks.iter().map(|(_, rate)| rate).sum::<f64>();
+        for (from, produced) in &sources {
+            for (to, consumed) in &sinks {
+                let rate = produced * consumed / total;
+                if rate > MIN_FLOW {
+                    flows.push(Flow {
+                        from: from.clone(),
+                        to: to.clone(),
+                        product,
+                        rate,
+                    });
+                }
+            }
+        }
+    }
+    flows
+}
+
+/// Every supply and output node the flows pass through, in order.
+fn terminals(flows: &[Flow]) -> Vec<Node> {
+    let mut nodes = Vec::new();
+    for node in flows.iter().flat_map(|f| [&f.from, &f.to]) {
+        if !matches!(node, Node::Group(_)) && !nodes.contains(node) {
+            nodes.push(node.clone());
+        }
+    }
+    nodes
+}
+
+fn node_id(node: &Node, terminals: &[Node]) -> String {
+    match node {
+        Node::Group(i) => format!("group{}", i),
+        _ => {
+            let i = terminals.iter().position(|n| n == node).unwrap_or(0);
+            format!("product{}", i)
+        }
+    }
+}
+
+/// The lines of text a node is labelled with.
+fn node_label(node: &Node, plan: &ProductionPlan) -> Vec<String> {
+    match node {
+        Node::Group(i) => {
+            let group = &plan.groups()[*i];
+            vec![
+                format!("{} × {}", round(group.count()), group.machine()),
+                group.recipe().to_owned(),
+            ]
+        }
+        Node::Supply(product) => vec![format!("{} (supplied)", product)],
+        Node::Output(product) => vec![format!("{} (output)", product)],
+    }
+}
+
+fn flow_label(flow: &Flow) -> String {
+    format!("{} {}/min", round(flow.rate), flow.product)
+}
+
+/// Rounds to two decimal places, which is plenty for a label.
+fn round(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+/// Renders `plan` in the Graphviz DOT language.
+pub fn to_dot(plan: &ProductionPlan) -> String {
+    let escape = |s: &str| s.replace('\\', "\\\\").replace('"', "\\\"");
+    let flows = flows(plan);
+    let terminals = terminals(&flows);
+
+    let mut dot = String::from("digraph production {\n    rankdir=LR;\n");
+    let groups = (0..plan.groups().len()).map(Node::Group);
+    for node in groups.chain(terminals.iter().cloned()) {
+        let label = node_label(&node, plan)
+            .iter()
+            .map(|line| escape(line))
+            .collect::<Vec<_>>()
+            .join("\\n");
+        let shape = match node {
+            Node::Group(_) => "box",
+            _ => "ellipse",
+        };
+        writeln!(
+            dot,
+            "    {} [label=\"{}\", shape={}];",
+            node_id(&node, &terminals),
+            label,
+            shape
+        )
+        .unwrap();
+    }
+    for flow in &flows {
+        writeln!(
+            dot,
+            "    {} -> {} [label=\"{}\"];",
+            node_id(&flow.from, &terminals),
+            node_id(&flow.to, &terminals),
+            escape(&flow_label(flow))
+        )
+        .unwrap();
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+/// Renders `plan` as a Mermaid flowchart.
+pub fn to_mermaid(plan: &ProductionPlan) -> String {
+    let escape = |s: &str| s.replace('"', "#quot;");
+    let flows = flows(plan);
+    let terminals = terminals(&flows);
+
+    let mut mermaid = String::from("flowchart LR\n");
+    let groups = (0..plan.groups().len()).map(Node::Group);
+    for node in groups.chain(terminals.iter().cloned()) {
+        let label = node_label(&node, plan)
+            .iter()
+            .map(|line| escape(line))
+            .collect::<Vec<_>>()
+            .join("<br/>");
+        let (open, close) = match node {
+            Node::Group(_) => ("[", "]"),
+            _ => ("([", "])"),
+        };
+        writeln!(
+            mermaid,
+            "    {}{}\"{}\"{}",
+            node_id(&node, &terminals),
+            open,
+            label,
+            close
+        )
+        .unwrap();
+    }
+    for flow in &flows {
+        writeln!(
+            mermaid,
+            "    {} -->|\"{}\"| {}",
+            node_id(&flow.from, &terminals),
+            escape(&flow_label(flow)),
+            node_id(&flow.to, &terminals)
+        )
+        .unwrap();
+    }
+    mermaid
+}
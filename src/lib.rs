@@ -1,2 +1,13 @@
+pub mod backend;
+pub mod cli;
+pub mod diagnosis;
+pub mod dump;
+pub mod error;
 pub mod factorio;
+pub mod graph;
+pub mod model_file;
+pub mod objective;
+pub mod plan;
+pub mod science;
 pub mod solver;
+pub mod validation;
@@ -1,6 +1,6 @@
-use color_eyre::{Report, Result};
+use color_eyre::{eyre::eyre, Report, Result};
 
-// use factorio_optimizer::*;
+use factorio_optimizer::cli;
 
 fn main() -> Result<(), Report> {
     if std::env::var("RUST_BACKTRACE").is_err() {
@@ -8,5 +8,6 @@ fn main() -> Result<(), Report> {
     }
     color_eyre::install()?;
 
-    Ok(())
+    let command = cli::parse_args(std::env::args().skip(1))?;
+    cli::run(&command, &mut std::io::stdout().lock()).map_err(|e| eyre!("{}", e))
 }
@@ -0,0 +1,104 @@
+//! Loading and saving models in the crate's own format, as JSON or TOML.
+//!
+//! A model file lists its recipes, products and machines. Products are
+//! referred to by name everywhere, so recipes read like
+//!
+//! ```json
+//! {
+//!   "name": "Iron gear wheel",
+//!   "production_time": 0.5,
+//!   "usage": { "Iron plate": 2.0 },
+//!   "production": { "Iron gear wheel": 1.0 }
+//! }
+//! ```
+//!
+//! Fluids are listed among the products as
+//! `{ "name": "Steam", "kind": "fluid", "temperature": 165 }`, and a fluid
+//! with a temperature is referred to as `Steam@165`. Recipes accepting a fluid
+//! over a range of temperatures list it by name alone, along with
+//! `"temperatures": { "Steam": { "min": 15, "max": 1000 } }`.
+//!
+//! Anything with a sensible default, such as a recipe's category or a
+//! machine's power draw, may be left out.
+//!
+//! The same model written as TOML reads like
+//!
+//! ```toml
+//! products = ["Iron plate", "Iron gear wheel"]
+//!
+//! [[recipes]]
+//! name = "Iron gear wheel"
+//! production_time = 0.5
+//!
+//! [recipes.usage]
+//! "Iron plate" = 2.0
+//!
+//! [recipes.production]
+//! "Iron gear wheel" = 1.0
+//! ```
+use std::error::Error;
+use std::path::Path;
+
+use crate::solver::Model;
+
+/// The formats model files may be written in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ModelFormat {
+    #[default]
+    Json,
+    Toml,
+}
+
+impl ModelFormat {
+    /// The format of the file at `path`: TOML for a `.toml` extension, JSON
+    /// otherwise.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Self {
+        match path.as_ref().extension().and_then(|e| e.to_str()) {
+            Some(extension) if extension.eq_ignore_ascii_case("toml") => ModelFormat::Toml,
+            _ => ModelFormat::Json,
+        }
+    }
+}
+
+/// Reads a model file from disk, in the format its extension calls for.
+pub fn load_model<P: AsRef<Path>>(path: P) -> Result<Model, Box<dyn Error>> {
+    let contents = std::fs::read_to_string(&path)?;
+    parse_model_as(&contents, ModelFormat::from_path(path))
+}
+
+/// Writes `model` to disk, in the format the extension of `path` calls for,
+/// replacing whatever was there.
+pub fn save_model<P: AsRef<Path>>(model: &Model, path: P) -> Result<(), Box<dyn Error>> {
+    let mut contents = write_model_as(model, ModelFormat::from_path(&path))?;
+    if !contents.ends_with('\n') {
+        contents.push('\n');
+    }
+    std::fs::write(path, contents)?;
+    Ok(())
+}
+
+/// Builds a model out of the contents of a model file in `format`.
+pub fn parse_model_as(contents: &str, format: ModelFormat) -> Result<Model, Box<dyn Error>> {
+    match format {
+        ModelFormat::Json => Ok(parse_model(contents)?),
+        ModelFormat::Toml => Ok(toml::from_str(contents)?),
+    }
+}
+
+/// Formats `model` in `format` the way [`save_model`] writes it.
+pub fn write_model_as(model: &Model, format: ModelFormat) -> Result<String, Box<dyn Error>> {
+    match format {
+        ModelFormat::Json => Ok(write_model(model)?),
+        ModelFormat::Toml => Ok(toml::to_string_pretty(model)?),
+    }
+}
+
+/// Builds a model out of the contents of a JSON model file.
+pub fn parse_model(json: &str) -> Result<Model, serde_json::Error> {
+    serde_json::from_str(json)
+}
+
+/// Formats `model` as JSON the way [`save_model`] writes it.
+pub fn write_model(model: &Model) -> Result<String, serde_json::Error> {
+    serde_json::to_string_pretty(model)
+}
@@ -0,0 +1,34 @@
+use crate::factorio::Product;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// What the solver minimises.
+///
+/// Every objective is a per-machine cost summed over all machines in the
+/// plan, so they can be freely combined.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub enum Objective {
+    /// The total number of machines.
+    #[default]
+    Machines,
+    /// The total electrical power drawn in kW, assuming every machine crafts
+    /// continuously.
+    Power,
+    /// The total pollution emitted per minute.
+    Pollution,
+    /// The total rate, in items per minute, at which the given raw resources
+    /// are produced by machines or supplied from outside the factory.
+    RawResources(Vec<Product>),
+    /// The total number of tiles covered by machines.
+    Area,
+    /// Maximise the net output of the given products, each multiplied by its
+    /// weight.  Unless every raw resource is limited with
+    /// [`Solver::add_supply_constraint`](crate::solver::Solver::add_supply_constraint),
+    /// the output is usually unbounded.
+    MaximiseOutput(Vec<(Product, f64)>),
+    /// A sum of objectives, each multiplied by its weight.
+    Weighted(Vec<(f64, Objective)>),
+    /// Minimise each objective in turn, without making any earlier one worse.
+    /// When nested inside another objective, only the first one counts.
+    Lexicographic(Vec<Objective>),
+}
@@ -0,0 +1,360 @@
+use std::collections::HashMap;
+
+use crate::factorio::{Loadout, Machine, Product, Recipe};
+use serde::Deserialize;
+use serde::Serialize;
+
+/// A group of identical machines all running the same recipe.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct RecipeGroup {
+    /// Name of the machine.
+    machine: String,
+    /// Name of the recipe the machines run.
+    recipe: String,
+    /// Modules and beacons every machine in the group uses.
+    loadout: Loadout,
+    /// How many machines run this recipe.
+    count: f64,
+    /// Power drawn by the whole group while crafting, in kW.
+    power: f64,
+    /// Pollution emitted by the whole group per minute.
+    pollution: f64,
+    /// How much of each product the whole group produces per minute, with
+    /// products it consumes counted as negative.
+    rates: HashMap<String, f64>,
+}
+
+impl RecipeGroup {
+    pub fn new(
+        machine: String,
+        recipe: String,
+        loadout: Loadout,
+        count: f64,
+        power: f64,
+        pollution: f64,
+        rates: HashMap<String, f64>,
+    ) -> Self {
+        Self {
+            machine,
+            recipe,
+            loadout,
+            count,
+            power,
+            pollution,
+            rates,
+        }
+    }
+
+    pub fn machine(&self) -> &str {
+        &self.machine
+    }
+
+    pub fn recipe(&self) -> &str {
+        &self.recipe
+    }
+
+    pub fn loadout(&self) -> &Loadout {
+        &self.loadout
+    }
+
+    pub fn count(&self) -> f64 {
+        self.count
+    }
+
+    pub fn power(&self) -> f64 {
+        self.power
+    }
+
+    pub fn pollution(&self) -> f64 {
+        self.pollution
+    }
+
+    pub fn rate_of(&self, product: &Product) -> f64 {
+        self.rates.get(&product.key()).copied().unwrap_or(0.0)
+    }
+
+    pub fn rates(&self) -> &HashMap<String, f64> {
+        &self.rates
+    }
+}
+
+/// The reduced cost of one group of machines: how much the objective would
+/// grow per machine forced into the group. It's zero for groups in the plan,
+/// and never negative.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ReducedCost {
+    machine: String,
+    recipe: String,
+    loadout: Loadout,
+    cost: f64,
+}
+
+impl ReducedCost {
+    pub fn new(machine: String, recipe: String, loadout: Loadout, cost: f64) -> Self {
+        Self {
+            machine,
+            recipe,
+            loadout,
+            cost,
+        }
+    }
+
+    pub fn machine(&self) -> &str {
+        &self.machine
+    }
+
+    pub fn recipe(&self) -> &str {
+        &self.recipe
+    }
+
+    pub fn loadout(&self) -> &Loadout {
+        &self.loadout
+    }
+
+    pub fn cost(&self) -> f64 {
+        self.cost
+    }
+}
+
+/// How the objective of a plan solved with continuous machine counts responds
+/// to its constraints.
+///
+/// Every price is the dual value of a constraint: how fast the objective grows
+/// as more of a product per minute is asked for. A constraint binds exactly
+/// when its price isn't zero. Where the plan sits right at a point where a
+/// different constraint would start to bind, e.g. a supply that's used up
+/// exactly, the price is one of those on either side of it.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Sensitivity {
+    /// Shadow price of each product: the cost of one more unit per minute of
+    /// it, on top of everything the plan already makes. Products with a
+    /// minimum output can count that extra unit towards it for free, so
+    /// asking more of them is priced by their minimum output price instead.
+    shadow_prices: HashMap<String, f64>,
+    /// Price of raising the minimum output of each product, never negative.
+    minimum_output_prices: HashMap<String, f64>,
+    /// Price of raising the maximum output of each product, never positive.
+    maximum_output_prices: HashMap<String, f64>,
+    /// Reduced cost of every (machine, recipe, loadout) group the solver could
+    /// have used.
+    reduced_costs: Vec<ReducedCost>,
+}
+
+impl Sensitivity {
+    pub fn new(
+        shadow_prices: HashMap<String, f64>,
+        minimum_output_prices: HashMap<String, f64>,
+        maximum_output_prices: HashMap<String, f64>,
+        reduced_costs: Vec<ReducedCost>,
+    ) -> Self {
+        Self {
+            shadow_prices,
+            minimum_output_prices,
+            maximum_output_prices,
+            reduced_costs,
+        }
+    }
+
+    pub fn shadow_price(&self, product: &Product) -> Option<f64> {
+        self.shadow_prices.get(&product.key()).copied()
+    }
+
+    pub fn minimum_output_price(&self, product: &Product) -> Option<f64> {
+        self.minimum_output_prices.get(&product.key()).copied()
+    }
+
+    pub fn maximum_output_price(&self, product: &Product) -> Option<f64> {
+        self.maximum_output_prices.get(&product.key()).copied()
+    }
+
+    /// Reduced cost of the machines of type `machine` running `recipe`, with
+    /// the cheapest of their loadouts.
+    pub fn reduced_cost(&self, machine: &Machine, recipe: &Recipe) -> Option<f64> {
+        self.reduced_costs
+            .iter()
+            .filter(|c| c.machine == machine.name() && c.recipe == recipe.name())
+            .map(|c| c.cost)
+            .reduce(f64::min)
+    }
+
+    pub fn shadow_prices(&self) -> &HashMap<String, f64> {
+        &self.shadow_prices
+    }
+
+    pub fn minimum_output_prices(&self) -> &HashMap<String, f64> {
+        &self.minimum_output_prices
+    }
+
+    pub fn maximum_output_prices(&self) -> &HashMap<String, f64> {
+        &self.maximum_output_prices
+    }
+
+    pub fn reduced_costs(&self) -> &[ReducedCost] {
+        &self.reduced_costs
+    }
+}
+
+/// The result of solving a model: which machines run which recipes, and how
+/// many of each product flows through the factory.
+///
+/// All rates are given in items per minute, and products are keyed by
+/// [`Product::key`].
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ProductionPlan {
+    /// The value of the objective function at the optimum.
+    objective: f64,
+    /// Every (machine, recipe, loadout) triple with a non-zero machine count.
+    groups: Vec<RecipeGroup>,
+    /// Surplus of each product absorbed by its overflow variable.
+    overflow: HashMap<String, f64>,
+    /// Amount of each product supplied from outside the factory.
+    supply: HashMap<String, f64>,
+    /// Production minus consumption of each product, not counting supply.
+    net_rates: HashMap<String, f64>,
+    /// Shadow prices and reduced costs, only for plans solved with
+    /// continuous machine counts.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sensitivity: Option<Sensitivity>,
+}
+
+impl ProductionPlan {
+    pub fn new(
+        objective: f64,
+        groups: Vec<RecipeGroup>,
+        overflow: HashMap<String, f64>,
+        supply: HashMap<String, f64>,
+        net_rates: HashMap<String, f64>,
+    ) -> Self {
+        Self {
+            objective,
+            groups,
+            overflow,
+            supply,
+            net_rates,
+            sensitivity: None,
+        }
+    }
+
+    pub fn with_sensitivity(mut self, sensitivity: Sensitivity) -> Self {
+        self.sensitivity = Some(sensitivity);
+        self
+    }
+
+    pub fn objective(&self) -> f64 {
+        self.objective
+    }
+
+    pub fn groups(&self) -> &[RecipeGroup] {
+        &self.groups
+    }
+
+    /// How many machines of type `machine` run `recipe`, whatever their
+    /// loadout.
+    pub fn machine_count(&self, machine: &Machine, recipe: &Recipe) -> f64 {
+        self.groups
+            .iter()
+            .filter(|g| g.machine == machine.name() && g.recipe == recipe.name())
+            .map(|g| g.count)
+            .sum()
+    }
+
+    /// Total number of machines across every recipe group.
+    pub fn total_machines(&self) -> f64 {
+        self.groups.iter().map(|g| g.count).sum()
+    }
+
+    /// Total electrical demand of the plan, in kW, assuming every machine
+    /// crafts continuously.
+    pub fn power(&self) -> f64 {
+        self.groups.iter().map(|g| g.power).sum()
+    }
+
+    /// Total pollution emitted by the plan per minute.
+    pub fn pollution(&self) -> f64 {
+        self.groups.iter().map(|g| g.pollution).sum()
+    }
+
+    pub fn overflow_of(&self, product: &Product) -> f64 {
+        self.overflow.get(&product.key()).copied().unwrap_or(0.0)
+    }
+
+    pub fn supply_of(&self, product: &Product) -> f64 {
+        self.supply.get(&product.key()).copied().unwrap_or(0.0)
+    }
+
+    pub fn net_rate_of(&self, product: &Product) -> f64 {
+        self.net_rates.get(&product.key()).copied().unwrap_or(0.0)
+    }
+
+    pub fn overflow(&self) -> &HashMap<String, f64> {
+        &self.overflow
+    }
+
+    pub fn supply(&self) -> &HashMap<String, f64> {
+        &self.supply
+    }
+
+    pub fn net_rates(&self) -> &HashMap<String, f64> {
+        &self.net_rates
+    }
+
+    pub fn sensitivity(&self) -> Option<&Sensitivity> {
+        self.sensitivity.as_ref()
+    }
+}
+
+/// A plan on the Pareto frontier between two objectives, see
+/// [`Solver::pareto_frontier`](crate::solver::Solver::pareto_frontier).
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ParetoPoint {
+    /// Value of the first objective for the plan.
+    first: f64,
+    /// Value of the second objective for the plan.
+    second: f64,
+    plan: ProductionPlan,
+}
+
+impl ParetoPoint {
+    pub fn new(first: f64, second: f64, plan: ProductionPlan) -> Self {
+        Self {
+            first,
+            second,
+            plan,
+        }
+    }
+
+    pub fn first(&self) -> f64 {
+        self.first
+    }
+
+    pub fn second(&self) -> f64 {
+        self.second
+    }
+
+    pub fn plan(&self) -> &ProductionPlan {
+        &self.plan
+    }
+}
+
+/// A plan for one rate of a product, see
+/// [`Solver::sweep`](crate::solver::Solver::sweep).
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SweepPoint {
+    /// Minimum output of the swept product, in units per minute.
+    rate: f64,
+    plan: ProductionPlan,
+}
+
+impl SweepPoint {
+    pub fn new(rate: f64, plan: ProductionPlan) -> Self {
+        Self { rate, plan }
+    }
+
+    pub fn rate(&self) -> f64 {
+        self.rate
+    }
+
+    pub fn plan(&self) -> &ProductionPlan {
+        &self.plan
+    }
+}
@@ -0,0 +1,150 @@
+//! Science-per-minute targets, which expand into production constraints on
+//! science packs, see [`Solver::add_science_target`].
+//!
+//! [`Solver::add_science_target`]: crate::solver::Solver::add_science_target
+use crate::factorio::Machine;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// The product labs make, one per unit of research. A target with labs is a
+/// production constraint on it rather than on the packs themselves.
+pub const RESEARCH: &str = "Research";
+
+/// The crafting category of the research recipe, which only labs run.
+pub const RESEARCH_CATEGORY: &str = "research";
+
+/// The science packs of the base game, from automation through space.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum SciencePack {
+    Automation,
+    Logistic,
+    Military,
+    Chemical,
+    Production,
+    Utility,
+    Space,
+}
+
+impl SciencePack {
+    /// Every science pack, in the order they're unlocked.
+    pub const ALL: [SciencePack; 7] = [
+        SciencePack::Automation,
+        SciencePack::Logistic,
+        SciencePack::Military,
+        SciencePack::Chemical,
+        SciencePack::Production,
+        SciencePack::Utility,
+        SciencePack::Space,
+    ];
+
+    /// The name of the pack as the game shows it, e.g. `Automation science
+    /// pack`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            SciencePack::Automation => "Automation science pack",
+            SciencePack::Logistic => "Logistic science pack",
+            SciencePack::Military => "Military science pack",
+            SciencePack::Chemical => "Chemical science pack",
+            SciencePack::Production => "Production science pack",
+            SciencePack::Utility => "Utility science pack",
+            SciencePack::Space => "Space science pack",
+        }
+    }
+
+    /// The name of the pack's prototype, as models imported from a data dump
+    /// call it, e.g. `automation-science-pack`.
+    pub fn prototype_name(&self) -> &'static str {
+        match self {
+            SciencePack::Automation => "automation-science-pack",
+            SciencePack::Logistic => "logistic-science-pack",
+            SciencePack::Military => "military-science-pack",
+            SciencePack::Chemical => "chemical-science-pack",
+            SciencePack::Production => "production-science-pack",
+            SciencePack::Utility => "utility-science-pack",
+            SciencePack::Space => "space-science-pack",
+        }
+    }
+}
+
+/// The labs researching with the packs of a [`ScienceTarget`].
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Labs {
+    lab: Machine,
+    /// Time a lab takes per unit of research in seconds, before bonuses.
+    unit_time: f64,
+    /// Research speed bonus, e.g. 0.5 for labs researching 50% faster.
+    #[serde(default)]
+    speed_bonus: f64,
+}
+
+impl Labs {
+    pub fn new(lab: Machine, unit_time: f64) -> Self {
+        Self {
+            lab,
+            unit_time,
+            speed_bonus: 0.0,
+        }
+    }
+
+    /// Sets the research speed bonus of the labs.
+    pub fn with_speed_bonus(mut self, speed_bonus: f64) -> Self {
+        self.speed_bonus = speed_bonus;
+        self
+    }
+
+    pub fn lab(&self) -> &Machine {
+        &self.lab
+    }
+
+    pub fn unit_time(&self) -> f64 {
+        self.unit_time
+    }
+
+    pub fn speed_bonus(&self) -> f64 {
+        self.speed_bonus
+    }
+
+    /// Time a lab takes per unit of research in seconds, with the speed bonus.
+    pub fn research_time(&self) -> f64 {
+        self.unit_time / (1.0 + self.speed_bonus)
+    }
+}
+
+/// A number of science packs per minute, the same for each of `packs`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ScienceTarget {
+    packs: Vec<SciencePack>,
+    /// Science per minute: how many of each pack are made every minute.
+    rate: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    labs: Option<Labs>,
+}
+
+impl ScienceTarget {
+    pub fn new(packs: Vec<SciencePack>, rate: f64) -> Self {
+        Self {
+            packs,
+            rate,
+            labs: None,
+        }
+    }
+
+    /// Plans labs consuming the packs too, instead of leaving them as output.
+    pub fn with_labs(mut self, labs: Labs) -> Self {
+        self.labs = Some(labs);
+        self
+    }
+
+    pub fn packs(&self) -> &[SciencePack] {
+        &self.packs
+    }
+
+    pub fn rate(&self) -> f64 {
+        self.rate
+    }
+
+    pub fn labs(&self) -> Option<&Labs> {
+        self.labs.as_ref()
+    }
+}
@@ -1,9 +1,15 @@
-use std::collections::HashMap;
-use std::error::Error;
+use std::collections::{HashMap, HashSet};
 
-use crate::factorio::{Machine, Product, Recipe};
-use good_lp::Expression;
-use good_lp::{constraint, default_solver, variable, variables, Solution, SolverModel};
+use crate::backend::{Comparison, Problem, SolverBackend};
+use crate::diagnosis::{self, ConflictingConstraint, Diagnosis};
+use crate::error::SolverError;
+use crate::factorio::{Loadout, Machine, Output, Product, Recipe};
+use crate::objective::Objective;
+use crate::plan::{ParetoPoint, ProductionPlan, RecipeGroup, ReducedCost, Sensitivity, SweepPoint};
+use crate::science::{Labs, SciencePack, ScienceTarget, RESEARCH, RESEARCH_CATEGORY};
+use crate::validation::{Issue, Validation};
+use good_lp::Solution;
+use good_lp::{Expression, Variable};
 use itertools::Itertools;
 use serde::Deserialize;
 use serde::Serialize;
@@ -11,7 +17,9 @@ use serde::Serialize;
 /// Our model, which consists of the recipies, products, and machines we've
 /// defined, as well as the necessary state to solve our model.
 #[derive(Serialize, Deserialize, Debug)]
+#[serde(from = "RawModel")]
 pub struct Model {
+    #[serde(rename = "recipes")]
     recipies: Vec<Recipe>,
     products: Vec<Product>,
     machines: Vec<Machine>,
@@ -19,34 +27,490 @@ pub struct Model {
 
 impl Model {
     /// Creates a new model.
-    pub fn new(recipies: Vec<Recipe>, products: Vec<Product>, machines: Vec<Machine>) -> Self {
+    ///
+    /// The products recipes use and produce are matched up with `products`,
+    /// so a recipe may refer to a fluid without saying it is one.
+    pub fn new(mut recipies: Vec<Recipe>, products: Vec<Product>, machines: Vec<Machine>) -> Self {
+        recipies
+            .iter_mut()
+            .for_each(|r| r.resolve_products(&products));
         Self {
             recipies,
             products,
             machines,
         }
     }
+
+    pub fn recipies(&self) -> &[Recipe] {
+        &self.recipies
+    }
+
+    pub fn products(&self) -> &[Product] {
+        &self.products
+    }
+
+    pub fn machines(&self) -> &[Machine] {
+        &self.machines
+    }
+
+    /// Every recipe, with each fluid it accepts over a range of temperatures
+    /// replaced by the products within that range. See [`Recipe::variants`].
+    pub fn recipe_variants(&self) -> Vec<Recipe> {
+        self.recipies
+            .iter()
+            .flat_map(|r| r.variants(&self.products))
+            .collect()
+    }
+
+    /// Checks the model for mistakes, such as recipes referring to unknown
+    /// products, names used twice, or recipes and machines that take no time
+    /// or run at no speed.
+    ///
+    /// [`Solver::solve`] refuses to solve a model with any errors, but
+    /// warnings are left to the caller.
+    pub fn validate(&self) -> Validation {
+        let mut issues = Vec::new();
+
+        let mut seen = HashSet::new();
+        for product in &self.products {
+            if !seen.insert(product) {
+                issues.push(Issue::DuplicateProduct(product.key()));
+            }
+        }
+        let mut seen = HashSet::new();
+        for machine in &self.machines {
+            if !seen.insert(machine.name()) {
+                issues.push(Issue::DuplicateMachine(machine.name().to_owned()));
+            }
+            let production_rate = machine.production_rate();
+            if !(production_rate > 0.0 && production_rate.is_finite()) {
+                issues.push(Issue::InvalidProductionRate {
+                    machine: machine.name().to_owned(),
+                    production_rate,
+                });
+            }
+        }
+
+        let mut seen = HashSet::new();
+        let mut used = HashSet::new();
+        for recipe in &self.recipies {
+            let name = recipe.name().to_owned();
+            if !seen.insert(recipe.name()) {
+                issues.push(Issue::DuplicateRecipe(name.clone()));
+            }
+            let production_time = recipe.production_time();
+            if !(production_time > 0.0 && production_time.is_finite()) {
+                issues.push(Issue::InvalidProductionTime {
+                    recipe: name.clone(),
+                    production_time,
+                });
+            }
+
+            let mut amounts = recipe
+                .usage()
+                .iter()
+                .map(|(p, amount)| (p, Output::new(*amount)))
+                .chain(recipe.production().iter().map(|(p, o)| (p, *o)))
+                .collect::<Vec<_>>();
+            amounts.sort_by_key(|(p, _)| p.key());
+            for (product, amount) in amounts {
+                used.insert(product.key());
+                let range = match product.temperature() {
+                    Some(_) => None,
+                    None => recipe.temperature_range(product.name()),
+                };
+                if let Some(range) = range {
+                    let matching = self
+                        .products
+                        .iter()
+                        .filter(|p| p.name() == product.name())
+                        .filter(|p| p.temperature().is_some_and(|t| range.contains(t)))
+                        .collect::<Vec<_>>();
+                    used.extend(matching.iter().map(|p| p.key()));
+                    if matching.is_empty() {
+                        issues.push(Issue::NoFluidInRange {
+                            recipe: name.clone(),
+                            fluid: product.name().to_owned(),
+                        });
+                    }
+                } else if !self.products.contains(product) {
+                    issues.push(Issue::UnknownProduct {
+                        recipe: name.clone(),
+                        product: product.key(),
+                    });
+                }
+                if amount.is_valid() {
+                    if amount.expected() == 0.0 {
+                        issues.push(Issue::ZeroAmount {
+                            recipe: name.clone(),
+                            product: product.key(),
+                        });
+                    }
+                } else if amount == Output::new(amount.amount_min()) {
+                    issues.push(Issue::InvalidAmount {
+                        recipe: name.clone(),
+                        product: product.key(),
+                        amount: amount.amount_min(),
+                    });
+                } else {
+                    issues.push(Issue::InvalidOutput {
+                        recipe: name.clone(),
+                        product: product.key(),
+                    });
+                }
+            }
+
+            if recipe.production().is_empty() {
+                issues.push(Issue::EmptyRecipe(name.clone()));
+            }
+            if !self.machines.iter().any(|m| m.can_craft(recipe)) {
+                issues.push(Issue::UncraftableRecipe(name));
+            }
+        }
+
+        issues.extend(
+            self.products
+                .iter()
+                .filter(|p| !used.contains(&p.key()))
+                .map(|p| Issue::UnusedProduct(p.key())),
+        );
+
+        Validation::new(issues)
+    }
+}
+
+/// A model as written in a model file, before [`Model::new`] has resolved its
+/// products.
+#[derive(Deserialize)]
+struct RawModel {
+    #[serde(rename = "recipes")]
+    recipies: Vec<Recipe>,
+    products: Vec<Product>,
+    machines: Vec<Machine>,
+}
+
+impl From<RawModel> for Model {
+    fn from(raw: RawModel) -> Self {
+        Model::new(raw.recipies, raw.products, raw.machines)
+    }
+}
+
+/// A bound on the net output of a product, i.e. how much more of it the
+/// factory produces than it consumes, in items per minute.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum OutputConstraint {
+    AtLeast(f64),
+    Exactly(f64),
+    AtMost(f64),
+}
+
+/// What happens to the surplus of a product, i.e. whatever of it leaves the
+/// factory beyond the minimum its output constraint asks for.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub enum DisposalPolicy {
+    /// Surplus is voided for free.
+    #[default]
+    Free,
+    /// No surplus may leave the factory, so it has to be consumed, e.g. by
+    /// cracking heavy oil.
+    Forbidden,
+    /// Every unit of surplus per minute costs this much in the objective.
+    Cost(f64),
+}
+
+/// Whether machine counts are whole numbers.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub enum SolveMode {
+    /// Every machine count is a whole number. Exact, but slow for large
+    /// models.
+    #[default]
+    Integer,
+    /// Machine counts may be fractional, e.g. 2.33 assemblers, which shows
+    /// the exact ratios between recipes.
+    Continuous,
+    /// Solves with fractional machine counts, then rounds every group up to
+    /// whole machines, some of which don't craft all the time. The objective
+    /// and power of the plan count the rounded machines, charging the time
+    /// they spend idle at their idle power.
+    RoundedUp,
+}
+
+/// Lower and upper bounds on the net output of a single product.
+#[derive(Clone, Copy, Debug, Default)]
+struct OutputBounds {
+    min: Option<f64>,
+    max: Option<f64>,
+}
+
+impl OutputBounds {
+    fn apply(&mut self, constraint: OutputConstraint) {
+        match constraint {
+            OutputConstraint::AtLeast(amount) => self.min = Some(amount),
+            OutputConstraint::Exactly(amount) => {
+                self.min = Some(amount);
+                self.max = Some(amount);
+            }
+            OutputConstraint::AtMost(amount) => self.max = Some(amount),
+        }
+    }
+}
+
+fn check_amount(product: &Product, amount: f64) -> Result<(), SolverError> {
+    if amount.is_finite() {
+        Ok(())
+    } else {
+        Err(SolverError::InvalidAmount {
+            product: product.key(),
+            amount,
+        })
+    }
+}
+
+/// Fails if `labs` would take no time, a negative amount of time, or an
+/// infinite one per unit of research.
+fn check_labs(labs: &Labs) -> Result<(), SolverError> {
+    let production_time = match labs.unit_time() {
+        time if !(time.is_finite() && time > 0.0) => time,
+        _ if !(labs.speed_bonus().is_finite() && labs.speed_bonus() > -1.0) => labs.research_time(),
+        _ => return Ok(()),
+    };
+    Err(SolverError::InvalidModel(vec![
+        Issue::InvalidProductionTime {
+            recipe: RESEARCH.to_owned(),
+            production_time,
+        },
+    ]))
+}
+
+/// What the science target of a [`Solver`] added to it, so that the next one
+/// can take out exactly that.
+#[derive(Debug, Default)]
+struct ScienceAdditions {
+    /// Each product whose minimum output the target set, along with the
+    /// minimum it had before.
+    minimums: Vec<(Product, Option<f64>)>,
+    /// The name of the lab the target added along with the research recipe.
+    lab: Option<String>,
+    /// Whether the target added the research product to the model.
+    research: bool,
+}
+
+/// Relative slack given to an already minimised objective while minimising the
+/// next one, so rounding in the solver can't make the problem infeasible.
+const OBJECTIVE_BOUND_TOLERANCE: f64 = 1e-6;
+
+/// Fractional machine counts within this much of a whole number are rounded
+/// to it rather than up, see [`SolveMode::RoundedUp`].
+const ROUNDING_TOLERANCE: f64 = 1e-6;
+
+/// The bound to put on an objective already minimised to `value`.
+fn objective_bound(value: f64) -> f64 {
+    value + OBJECTIVE_BOUND_TOLERANCE * value.abs().max(1.0)
+}
+
+/// A group of machines sharing one decision variable: how many machines of
+/// one type run one recipe with one loadout.
+struct MachineGroup<'a> {
+    machine: &'a Machine,
+    recipe: &'a Recipe,
+    loadout: &'a Loadout,
+    variable: Variable,
+}
+
+impl MachineGroup<'_> {
+    /// How many times per minute a single machine of this group runs its
+    /// recipe.
+    fn crafts_per_minute(&self) -> f64 {
+        self.machine.crafting_speed_with(self.loadout) * (60.0 / self.recipe.production_time())
+    }
+
+    /// Amount of `product` a single machine of this group produces per minute.
+    fn production_of(&self, product: &Product) -> f64 {
+        let productivity = self.machine.productivity_with(self.loadout);
+        self.recipe
+            .production_with(product, productivity)
+            .unwrap_or(0.0)
+            * self.crafts_per_minute()
+    }
+
+    /// Amount of `product` a single machine of this group consumes per minute.
+    fn usage_of(&self, product: &Product) -> f64 {
+        self.recipe.usage_of(product).unwrap_or(0.0) * self.crafts_per_minute()
+    }
+
+    /// Power drawn by a single machine of this group, in kW.
+    fn power(&self) -> f64 {
+        self.machine.active_power_with(self.loadout)
+    }
+
+    /// Power drawn by a single idle machine of this group, in kW.
+    fn idle_power(&self) -> f64 {
+        self.machine.idle_power_with(self.loadout)
+    }
+
+    /// Pollution emitted per minute by a single machine of this group.
+    fn pollution(&self) -> f64 {
+        self.machine.pollution_with(self.loadout)
+    }
+
+    /// Contribution of a single machine of this group to `objective`, not
+    /// counting its modules.
+    fn cost(&self, objective: &Objective) -> f64 {
+        match objective {
+            Objective::Machines => 1.0,
+            Objective::Power => self.power(),
+            Objective::Pollution => self.pollution(),
+            Objective::RawResources(products) => {
+                products.iter().map(|p| self.production_of(p)).sum()
+            }
+            Objective::Area => self.machine.area(),
+            Objective::MaximiseOutput(products) => products
+                .iter()
+                .map(|(p, weight)| -weight * (self.production_of(p) - self.usage_of(p)))
+                .sum(),
+            Objective::Weighted(objectives) => objectives
+                .iter()
+                .map(|(weight, o)| weight * self.cost(o))
+                .sum(),
+            Objective::Lexicographic(objectives) => {
+                objectives.first().map_or(0.0, |o| self.cost(o))
+            }
+        }
+    }
+
+    /// Contribution of a single idle machine of this group to `objective`,
+    /// not counting its modules. Idle machines still take up space and draw
+    /// their idle power, but make and emit nothing.
+    fn idle_cost(&self, objective: &Objective) -> f64 {
+        match objective {
+            Objective::Machines => 1.0,
+            Objective::Power => self.idle_power(),
+            Objective::Area => self.machine.area(),
+            Objective::Pollution | Objective::RawResources(_) | Objective::MaximiseOutput(_) => 0.0,
+            Objective::Weighted(objectives) => objectives
+                .iter()
+                .map(|(weight, o)| weight * self.idle_cost(o))
+                .sum(),
+            Objective::Lexicographic(objectives) => {
+                objectives.first().map_or(0.0, |o| self.idle_cost(o))
+            }
+        }
+    }
+}
+
+/// Contribution of each unit of `product` supplied per minute from outside the
+/// factory to `objective`.
+fn supply_cost(objective: &Objective, product: &Product) -> f64 {
+    match objective {
+        Objective::RawResources(products) => {
+            products.iter().filter(|p| *p == product).count() as f64
+        }
+        Objective::Machines
+        | Objective::Power
+        | Objective::Pollution
+        | Objective::Area
+        | Objective::MaximiseOutput(_) => 0.0,
+        Objective::Weighted(objectives) => objectives
+            .iter()
+            .map(|(weight, o)| weight * supply_cost(o, product))
+            .sum(),
+        Objective::Lexicographic(objectives) => {
+            objectives.first().map_or(0.0, |o| supply_cost(o, product))
+        }
+    }
+}
+
+/// The problem of minimising one objective, along with where the model ended
+/// up in it.
+struct BuiltProblem<'a> {
+    problem: Problem,
+    objective: Expression,
+    groups: Vec<MachineGroup<'a>>,
+    overflow: HashMap<&'a Product, Variable>,
+    supply: HashMap<&'a Product, Variable>,
+    /// Where the bound on each earlier objective ended up in the problem, in
+    /// the order they were given.
+    bound_rows: Vec<usize>,
+    /// Where each constraint ended up in the problem, by product, to price
+    /// it by or to move its right-hand side.
+    balance_rows: HashMap<String, usize>,
+    minimum_rows: HashMap<String, usize>,
+    maximum_rows: HashMap<String, usize>,
+    disposal_rows: HashMap<String, usize>,
 }
 
 /// A solver for our model.
 ///
 /// Our constants (invariant over the lifetime of the model) are the following:
-/// - B_m -> production bonus of a machine
-/// - P_rp -> how much of product p is produced in recipe r
+/// - S_ml -> crafting speed of machine m using loadout l
+/// - Q_ml -> productivity multiplier of machine m using loadout l, which only
+///   scales its output, and only the part of it that isn't catalyst, so
+///   Q_ml P_rp stands for P_rp + (Q_ml - 1) max(0, P_rp - catalyst)
+/// - K_l -> number of modules in loadout l
+/// - W_ml -> power drawn by machine m using loadout l
+/// - P_rp -> how much of product p is produced in recipe r, on average for
+///   recipes yielding a range of amounts or with a probability
 /// - C_rp -> how much of product p is consumed in recipe r
 ///
 /// Our variables are the following:
 ///
-/// - M_mr -> how many machines of type m produce under recipe r
+/// - M_mrl -> how many machines of type m produce under recipe r using loadout
+///   l, only for machines whose crafting categories include the category of r
+///   and whose module slots fit l, a whole number unless another
+///   [`SolveMode`] is set with [`Solver::set_mode`]
+/// - O_p -> surplus of product p leaving the factory
+/// - U_p -> amount of product p supplied from outside the factory, only for
+///   products with a supply constraint, bounded by 0 <= U_p <= L_p
 ///
 /// Our one required constraint is the following:
 ///
-/// - sum(B_m M_mr P_rp for m in M for r in R) - sum(B_m M_mr C_rp for m in M for r in R) == 0 for p in P
+/// - sum(S_ml Q_ml M_mrl P_rp for m, r, l) - sum(S_ml M_mrl C_rp for m, r, l) + U_p == O_p for p in P
+///
+/// Output constraints bound the net output of a product, which is the same
+/// sum of production minus consumption, without U_p.
+/// Pipe throughputs bound the production of a fluid plus U_p, everything of
+/// it that flows through the pipes.
+///
+/// Recipes accepting a fluid over a range of temperatures are split into one
+/// recipe per fluid product within the range beforehand, see
+/// [`Model::recipe_variants`].
+///
+/// Every machine may use its own loadout, or any of the candidate loadouts
+/// added with [`Solver::add_loadout`].  The objective is
+/// `sum((c_mrl + module_cost * K_l) M_mrl) + sum(R_p U_p) + sum(D_p (O_p - N_p))`,
+/// where c_mrl is the cost of a single machine under the chosen
+/// [`Objective`], e.g. 1 when minimising machines and W_ml when minimising
+/// power, and R_p is the cost of supplying a unit of p, e.g. 1 for the raw
+/// resources of [`Objective::RawResources`].  Lexicographic
+/// objectives solve once per objective, bounding each solved objective by its
+/// optimum.
 ///
+/// Surplus is whatever of a product leaves the factory beyond N_p, the minimum
+/// net output asked for it (or 0).  Its [`DisposalPolicy`] decides what it
+/// costs: D_p is the cost per unit of surplus, or 0 when it's voided for free,
+/// and products whose disposal is forbidden are bounded by O_p <= N_p.
+///
+/// The problem is solved with a [`SolverBackend`], CBC when it's installed or
+/// microlp otherwise, which can be changed with [`Solver::set_backend`].
 #[derive(Debug)]
 pub struct Solver {
     model: Model,
-    production_constraints: HashMap<Product, f64>,
+    production_constraints: HashMap<Product, OutputBounds>,
+    /// Most of each product that may be supplied from outside per minute.
+    supply_constraints: HashMap<Product, f64>,
+    /// Most of each fluid the pipes of the factory may carry per minute.
+    pipe_throughputs: HashMap<Product, f64>,
+    /// Candidate loadouts the solver may choose from for each machine group.
+    loadouts: Vec<Loadout>,
+    /// Objective cost of a single module, in the units of the objective.
+    module_cost: f64,
+    /// How the surplus of each product is disposed of, if not for free.
+    disposal_policies: HashMap<Product, DisposalPolicy>,
+    objective: Objective,
+    mode: SolveMode,
+    backend: SolverBackend,
+    /// What the latest science target added.
+    science: ScienceAdditions,
 }
 
 impl Solver {
@@ -54,133 +518,987 @@ impl Solver {
         Self {
             model,
             production_constraints: HashMap::new(),
+            supply_constraints: HashMap::new(),
+            pipe_throughputs: HashMap::new(),
+            loadouts: Vec::new(),
+            module_cost: 0.0,
+            disposal_policies: HashMap::new(),
+            objective: Objective::default(),
+            mode: SolveMode::default(),
+            backend: SolverBackend::default(),
+            science: ScienceAdditions::default(),
+        }
+    }
+
+    /// Requires the factory to export at least `amount_per_minute` of
+    /// `product`, on top of whatever it consumes itself.
+    pub fn add_production_constraint(
+        &mut self,
+        product: Product,
+        amount_per_minute: f64,
+    ) -> Result<(), SolverError> {
+        self.add_output_constraint(product, OutputConstraint::AtLeast(amount_per_minute))
+    }
+
+    /// Bounds the net output of `product`.  A later constraint of the same
+    /// kind on the same product replaces the earlier one.
+    pub fn add_output_constraint(
+        &mut self,
+        product: Product,
+        constraint: OutputConstraint,
+    ) -> Result<(), SolverError> {
+        self.check_product(&product)?;
+        let amount = match constraint {
+            OutputConstraint::AtLeast(amount)
+            | OutputConstraint::Exactly(amount)
+            | OutputConstraint::AtMost(amount) => amount,
+        };
+        check_amount(&product, amount)?;
+        self.production_constraints
+            .entry(product)
+            .or_default()
+            .apply(constraint);
+        Ok(())
+    }
+
+    /// Requires the factory to make `target.rate()` of each of its science
+    /// packs per minute, looked up in the model by either their own name or
+    /// their prototype name.
+    ///
+    /// Without labs, each pack gets a production constraint of its own. With
+    /// labs, the model gains a lab running a [`RESEARCH`] recipe that uses
+    /// one of each pack per unit, and the rate of research is constrained
+    /// instead, so the plan includes the labs along with their power. The lab
+    /// and the research recipe can't share their names with a machine or
+    /// recipe of the model.
+    ///
+    /// A later target replaces an earlier one: the minimum outputs it set go
+    /// back to what they were before, and its lab, research recipe and
+    /// research product are taken out of the model again.
+    pub fn add_science_target(&mut self, target: &ScienceTarget) -> Result<(), SolverError> {
+        let packs = target
+            .packs()
+            .iter()
+            .map(|pack| self.science_pack(*pack))
+            .collect::<Result<Vec<_>, _>>()?;
+        let research = Product::new(RESEARCH.to_owned());
+
+        let Some(labs) = target.labs() else {
+            for pack in &packs {
+                check_amount(pack, target.rate())?;
+            }
+            self.remove_science();
+            for pack in packs {
+                self.set_science_minimum(pack, target.rate());
+            }
+            return Ok(());
+        };
+
+        check_amount(&research, target.rate())?;
+        check_labs(labs)?;
+        let lab = labs.lab();
+        let added_lab = self.science.lab.as_deref();
+        let mut issues = Vec::new();
+        if added_lab.is_none() && self.model.recipies.iter().any(|r| r.name() == RESEARCH) {
+            issues.push(Issue::DuplicateRecipe(RESEARCH.to_owned()));
+        }
+        if added_lab != Some(lab.name()) && self.model.machines.contains(lab) {
+            issues.push(Issue::DuplicateMachine(lab.name().to_owned()));
+        }
+        if !issues.is_empty() {
+            return Err(SolverError::InvalidModel(issues));
+        }
+
+        let recipe = Recipe::new(
+            RESEARCH.to_owned(),
+            labs.research_time(),
+            packs.into_iter().map(|pack| (pack, 1.0)).collect(),
+            HashMap::from([(research.clone(), 1.0)]),
+        )
+        .with_category(RESEARCH_CATEGORY.to_owned());
+        let lab = lab.clone().with_crafting_categories([RESEARCH_CATEGORY]);
+
+        self.remove_science();
+        self.model.recipies.push(recipe);
+        self.science.lab = Some(lab.name().to_owned());
+        self.model.machines.push(lab);
+        if !self.model.products.contains(&research) {
+            self.model.products.push(research.clone());
+            self.science.research = true;
+        }
+        self.set_science_minimum(research, target.rate());
+        Ok(())
+    }
+
+    /// Sets the minimum output of `product` for a science target, keeping the
+    /// one it replaces.
+    fn set_science_minimum(&mut self, product: Product, amount_per_minute: f64) {
+        let bounds = self
+            .production_constraints
+            .entry(product.clone())
+            .or_default();
+        self.science.minimums.push((product, bounds.min));
+        bounds.apply(OutputConstraint::AtLeast(amount_per_minute));
+    }
+
+    /// Takes out everything the latest science target added.
+    fn remove_science(&mut self) {
+        let added = std::mem::take(&mut self.science);
+        for (product, min) in added.minimums.into_iter().rev() {
+            let bounds = self
+                .production_constraints
+                .entry(product.clone())
+                .or_default();
+            bounds.min = min;
+            if bounds.max.is_none() && min.is_none() {
+                self.production_constraints.remove(&product);
+            }
+        }
+        if let Some(lab) = added.lab {
+            self.model.recipies.retain(|r| r.name() != RESEARCH);
+            self.model.machines.retain(|m| m.name() != lab);
+        }
+        if added.research {
+            let research = Product::new(RESEARCH.to_owned());
+            self.model.products.retain(|p| *p != research);
+        }
+    }
+
+    /// The product `pack` is called in the model.
+    fn science_pack(&self, pack: SciencePack) -> Result<Product, SolverError> {
+        self.model
+            .products
+            .iter()
+            .find(|p| {
+                p.name().eq_ignore_ascii_case(pack.name()) || p.name() == pack.prototype_name()
+            })
+            .cloned()
+            .ok_or_else(|| SolverError::UnknownProduct(pack.name().to_owned()))
+    }
+
+    /// Makes up to `amount_per_minute` of `product` available from outside the
+    /// factory, e.g. ore delivered by train from existing outposts.
+    pub fn add_supply_constraint(
+        &mut self,
+        product: Product,
+        amount_per_minute: f64,
+    ) -> Result<(), SolverError> {
+        self.check_product(&product)?;
+        check_amount(&product, amount_per_minute)?;
+        if amount_per_minute < 0.0 {
+            return Err(SolverError::InvalidAmount {
+                product: product.key(),
+                amount: amount_per_minute,
+            });
+        }
+        self.supply_constraints.insert(product, amount_per_minute);
+        Ok(())
+    }
+
+    /// Limits the pipes carrying the fluid `product` to `amount_per_minute`:
+    /// everything made of it by machines, plus whatever is supplied, has to
+    /// fit through them, e.g. 72,000 for a single pipeline carrying 1,200
+    /// units a second. A later limit on the same fluid replaces the earlier
+    /// one.
+    pub fn add_pipe_throughput(
+        &mut self,
+        product: Product,
+        amount_per_minute: f64,
+    ) -> Result<(), SolverError> {
+        self.check_product(&product)?;
+        check_amount(&product, amount_per_minute)?;
+        if amount_per_minute < 0.0 {
+            return Err(SolverError::InvalidAmount {
+                product: product.key(),
+                amount: amount_per_minute,
+            });
+        }
+        self.pipe_throughputs.insert(product, amount_per_minute);
+        Ok(())
+    }
+
+    fn check_product(&self, product: &Product) -> Result<(), SolverError> {
+        if self.model.products.contains(product) {
+            Ok(())
+        } else {
+            Err(SolverError::UnknownProduct(product.key()))
+        }
+    }
+
+    /// Adds a loadout the solver may pick for any machine it fits in.
+    pub fn add_loadout(&mut self, loadout: Loadout) {
+        if !self.loadouts.contains(&loadout) {
+            self.loadouts.push(loadout);
+        }
+    }
+
+    /// Sets how much a single module costs in the objective, e.g. in machines
+    /// when minimising machines or in kW when minimising power.
+    pub fn set_module_cost(&mut self, module_cost: f64) {
+        self.module_cost = module_cost;
+    }
+
+    /// Sets what the solver minimises.
+    pub fn set_objective(&mut self, objective: Objective) {
+        self.objective = objective;
+    }
+
+    /// Sets whether machine counts are whole numbers.
+    pub fn set_mode(&mut self, mode: SolveMode) {
+        self.mode = mode;
+    }
+
+    /// Sets the engine the linear programs are solved with.
+    pub fn set_backend(&mut self, backend: SolverBackend) {
+        self.backend = backend;
+    }
+
+    /// The mode every stage but the last of a lexicographic objective is
+    /// solved in, and the one [`Solver::diagnose`] checks feasibility in.
+    /// Rounding up only ever happens once, at the very end.
+    fn relaxed_mode(&self) -> SolveMode {
+        match self.mode {
+            SolveMode::Integer => SolveMode::Integer,
+            SolveMode::Continuous | SolveMode::RoundedUp => SolveMode::Continuous,
+        }
+    }
+
+    /// Sets how the surplus of `product` is disposed of. A cost is given in
+    /// the units of the objective per unit of surplus per minute.
+    pub fn set_disposal_policy(
+        &mut self,
+        product: Product,
+        policy: DisposalPolicy,
+    ) -> Result<(), SolverError> {
+        self.check_product(&product)?;
+        if let DisposalPolicy::Cost(cost) = policy {
+            if !(cost >= 0.0 && cost.is_finite()) {
+                return Err(SolverError::InvalidAmount {
+                    product: product.key(),
+                    amount: cost,
+                });
+            }
         }
+        self.disposal_policies.insert(product, policy);
+        Ok(())
+    }
+
+    pub fn disposal_policy(&self, product: &Product) -> DisposalPolicy {
+        self.disposal_policies
+            .get(product)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// How much of `product` has to leave the factory before any more of it
+    /// counts as surplus.
+    fn minimum_output(&self, product: &Product) -> f64 {
+        self.production_constraints
+            .get(product)
+            .and_then(|bounds| bounds.min)
+            .map_or(0.0, |min| min.max(0.0))
+    }
+
+    /// Contribution of a single machine of `group` to `objective`, including
+    /// its modules.
+    fn machine_cost(&self, objective: &Objective, group: &MachineGroup) -> f64 {
+        group.cost(objective) + self.module_cost * group.loadout.module_count() as f64
+    }
+
+    /// Contribution of a single idle machine of `group` to `objective`,
+    /// including its modules.
+    fn idle_machine_cost(&self, objective: &Objective, group: &MachineGroup) -> f64 {
+        group.idle_cost(objective) + self.module_cost * group.loadout.module_count() as f64
+    }
+
+    /// The expression minimised for `objective`, including module, supply and
+    /// disposal costs.
+    fn objective_expression(
+        &self,
+        objective: &Objective,
+        groups: &[MachineGroup],
+        overflow: &HashMap<&Product, Variable>,
+        supply: &HashMap<&Product, Variable>,
+    ) -> Expression {
+        let machines = groups
+            .iter()
+            .map(|g| self.machine_cost(objective, g) * g.variable)
+            .fold(Expression::from_other_affine(0u8), |acc, x| acc + x);
+        let supplied = supply
+            .iter()
+            .map(|(p, v)| supply_cost(objective, p) * *v)
+            .fold(machines, |acc, x| acc + x);
+
+        overflow
+            .iter()
+            .filter_map(|(p, v)| match self.disposal_policy(p) {
+                DisposalPolicy::Cost(cost) => Some(cost * (*v - self.minimum_output(p))),
+                DisposalPolicy::Free | DisposalPolicy::Forbidden => None,
+            })
+            .fold(supplied, |acc, x| acc + x)
+    }
+
+    /// Production of `product` by every machine, per minute.
+    fn production_rate_expression(&self, product: &Product, groups: &[MachineGroup]) -> Expression {
+        groups
+            .iter()
+            .filter(|g| g.recipe.production_of(product).is_some())
+            .map(|g| g.production_of(product) * g.variable)
+            .fold(Expression::from_other_affine(0), |acc, x| acc + x)
+    }
+
+    /// Production minus consumption of `product`, per minute.
+    fn net_rate_expression(&self, product: &Product, groups: &[MachineGroup]) -> Expression {
+        let production_rate = self.production_rate_expression(product, groups);
+
+        let consumption_rate: Expression = groups
+            .iter()
+            .filter(|g| g.recipe.usage_of(product).is_some())
+            .map(|g| g.usage_of(product) * g.variable)
+            .fold(Expression::from_other_affine(0), |acc, x| acc + x);
+
+        production_rate - consumption_rate
     }
 
-    pub fn add_production_constraint(&mut self, product: Product, amount_per_minute: f64) {
-        if self.model.products.contains(&product) {
-            self.production_constraints
-                .insert(product, amount_per_minute);
+    /// The loadouts machines of type `machine` may use.
+    fn loadouts_for<'a>(&'a self, machine: &'a Machine) -> impl Iterator<Item = &'a Loadout> {
+        std::iter::once(machine.loadout()).chain(
+            self.loadouts
+                .iter()
+                .filter(move |l| *l != machine.loadout() && machine.accepts(l)),
+        )
+    }
+
+    /// Solves the model, returning the machines needed to satisfy every
+    /// production constraint.
+    ///
+    /// For lexicographic objectives, the objective of the plan is the value
+    /// of the last objective minimised.
+    pub fn solve(&self) -> Result<ProductionPlan, SolverError> {
+        self.check_model()?;
+        Ok(self.solve_stages(&self.stages(), Vec::new(), false)?.0)
+    }
+
+    /// Solves the model like [`Solver::solve`], along with how its objective
+    /// responds to each constraint, see [`Sensitivity`]. Prices only make
+    /// sense for fractional machine counts, so every stage is solved in
+    /// [`SolveMode::Continuous`] whatever the mode of the solver.
+    ///
+    /// Prices are found by solving the dual of the last stage, which takes
+    /// about as long again as [`Solver::solve`].
+    pub fn solve_with_sensitivity(&self) -> Result<ProductionPlan, SolverError> {
+        self.check_model()?;
+        Ok(self.solve_stages(&self.stages(), Vec::new(), true)?.0)
+    }
+
+    /// Plans for each of `rates` as the minimum output of `product`, in the
+    /// same order, to see how machines, power and raw inputs scale with it.
+    /// Any other constraint on `product` still holds.
+    ///
+    /// The problem for each stage of the objective is only built once, then
+    /// solved again for every rate with its bounds moved. Rates that can't be
+    /// planned for, e.g. because they need more than the supply allows, get
+    /// their own error without stopping the rest of the sweep; errors with
+    /// the model or the rates themselves fail the whole sweep.
+    pub fn sweep(
+        &self,
+        product: &Product,
+        rates: &[f64],
+    ) -> Result<Vec<Result<SweepPoint, SolverError>>, SolverError> {
+        self.check_model()?;
+        self.check_product(product)?;
+        for rate in rates {
+            check_amount(product, *rate)?;
         }
+
+        let stages = self.stages();
+        let recipies = self.model.recipe_variants();
+        let mut problems = stages
+            .iter()
+            .enumerate()
+            .map(|(i, stage)| {
+                let mode = self.stage_mode(i, stages.len());
+                let bounds = stages[..i].iter().map(|s| (*s, 0.0)).collect::<Vec<_>>();
+                let mut built = self.build(&recipies, stage, mode, &bounds, &|_| true);
+                if !built.minimum_rows.contains_key(&product.key()) {
+                    let net_rate = self.net_rate_expression(product, &built.groups);
+                    let row =
+                        built
+                            .problem
+                            .add_constraint(net_rate, Comparison::GreaterOrEqual, 0.0);
+                    built.minimum_rows.insert(product.key(), row);
+                }
+                let objective = built.objective.clone();
+                (mode, built, objective)
+            })
+            .collect::<Vec<_>>();
+
+        // Every objective charges for the surplus of `product` over its
+        // minimum output, which moves with the rate.
+        let surplus_offset = |rate: f64| match self.disposal_policy(product) {
+            DisposalPolicy::Cost(cost) => cost * (self.minimum_output(product) - rate.max(0.0)),
+            DisposalPolicy::Free | DisposalPolicy::Forbidden => 0.0,
+        };
+
+        let solve_rate = |problems: &mut [(SolveMode, BuiltProblem, Expression)], rate: f64| {
+            let offset = surplus_offset(rate);
+            let mut plan = None;
+            let mut optima = Vec::new();
+            for ((mode, built, objective), stage) in problems.iter_mut().zip(&stages) {
+                let row = built.minimum_rows[&product.key()];
+                built.problem.set_rhs(row, rate);
+                if let Some(row) = built.disposal_rows.get(&product.key()) {
+                    built.problem.set_rhs(*row, rate.max(0.0));
+                }
+                for (row, optimum) in built.bound_rows.iter().zip(&optima) {
+                    built
+                        .problem
+                        .set_rhs(*row, objective_bound(*optimum) - offset);
+                }
+                built.objective = objective.clone() + offset;
+                let solution = built.problem.minimise(&built.objective, self.backend)?;
+                let stage_plan = self.plan_from(built, &solution, stage, *mode);
+                optima.push(stage_plan.objective());
+                plan = Some(stage_plan);
+            }
+            let plan = plan.expect("there is always at least one stage");
+            Ok(SweepPoint::new(rate, plan))
+        };
+
+        Ok(rates
+            .iter()
+            .map(|rate| solve_rate(&mut problems, *rate))
+            .collect())
+    }
+
+    /// The objectives to minimise in turn, each without making the ones before
+    /// it worse.
+    fn stages(&self) -> Vec<&Objective> {
+        match &self.objective {
+            Objective::Lexicographic(objectives) if !objectives.is_empty() => {
+                objectives.iter().collect()
+            }
+            objective => vec![objective],
+        }
+    }
+
+    /// The mode stage `i` out of `stages` is solved in: only the last one is
+    /// solved in the mode asked for.
+    fn stage_mode(&self, i: usize, stages: usize) -> SolveMode {
+        match i + 1 == stages {
+            true => self.mode,
+            false => self.relaxed_mode(),
+        }
+    }
+
+    /// Minimises each of `stages` in turn, keeping every objective in
+    /// `bounds` at or below its paired value and never making an earlier
+    /// stage worse. Returns the plan of the last stage along with the optimum
+    /// of every stage.
+    ///
+    /// If `analyse` is set, every stage is solved in [`SolveMode::Continuous`]
+    /// and the plan comes with its [`Sensitivity`].
+    fn solve_stages<'a>(
+        &self,
+        stages: &[&'a Objective],
+        mut bounds: Vec<(&'a Objective, f64)>,
+        analyse: bool,
+    ) -> Result<(ProductionPlan, Vec<f64>), SolverError> {
+        let mut plan = None;
+        let mut optima = Vec::new();
+        for (i, stage) in stages.iter().enumerate() {
+            let last = i + 1 == stages.len();
+            let mode = match analyse {
+                true => SolveMode::Continuous,
+                false => self.stage_mode(i, stages.len()),
+            };
+            let stage_plan = self.solve_with(stage, mode, &bounds, &|_| true, analyse && last)?;
+            bounds.push((stage, stage_plan.objective()));
+            optima.push(stage_plan.objective());
+            plan = Some(stage_plan);
+        }
+        let plan = plan.expect("there is always at least one stage");
+        Ok((plan, optima))
     }
 
-    pub fn solve(&self) -> Result<f64, Box<dyn Error>> {
-        let mut vars = variables! {};
-        let machines = self
+    /// Plans trading `first` off against `second`, none of which is beaten at
+    /// both by another, ordered from the best at `first` to the best at
+    /// `second`.
+    ///
+    /// After minimising each objective on its own, `second` is bounded at
+    /// evenly spaced values between the two, `points` in all counting both
+    /// ends, and `first` is minimised under each bound. Bounds that land on
+    /// the same plan, as whole machine counts often make them do, only yield
+    /// it once. Fewer than two `points` are rejected with
+    /// [`SolverError::TooFewPoints`].
+    pub fn pareto_frontier(
+        &self,
+        first: &Objective,
+        second: &Objective,
+        points: usize,
+    ) -> Result<Vec<ParetoPoint>, SolverError> {
+        self.check_model()?;
+        if points < 2 {
+            return Err(SolverError::TooFewPoints(points));
+        }
+
+        let point = |(plan, optima): (ProductionPlan, Vec<f64>)| {
+            ParetoPoint::new(optima[0], optima[1], plan)
+        };
+        let best_first = point(self.solve_stages(&[first, second], Vec::new(), false)?);
+        let (plan, optima) = self.solve_stages(&[second, first], Vec::new(), false)?;
+        let best_second = ParetoPoint::new(optima[1], optima[0], plan);
+
+        let steps = points - 1;
+        let (from, to) = (best_first.second(), best_second.second());
+        let mut candidates = vec![best_first];
+        for step in 1..steps {
+            let bound = from + (to - from) * step as f64 / steps as f64;
+            candidates.push(point(self.solve_stages(
+                &[first, second],
+                vec![(second, bound)],
+                false,
+            )?));
+        }
+        candidates.push(best_second);
+
+        // Each candidate is no worse at `first` than the ones before it, so it
+        // only belongs on the frontier if it's strictly better at `second`.
+        let mut frontier: Vec<ParetoPoint> = Vec::new();
+        for candidate in candidates {
+            let dominated = frontier.last().is_some_and(|last| {
+                let tolerance = OBJECTIVE_BOUND_TOLERANCE * last.second().abs().max(1.0);
+                candidate.second() >= last.second() - tolerance
+            });
+            if !dominated {
+                frontier.push(candidate);
+            }
+        }
+        Ok(frontier)
+    }
+
+    /// Fails if the model has any validation errors.
+    fn check_model(&self) -> Result<(), SolverError> {
+        let validation = self.model.validate();
+        if validation.is_valid() {
+            Ok(())
+        } else {
+            Err(SolverError::InvalidModel(
+                validation.errors().cloned().collect(),
+            ))
+        }
+    }
+
+    /// Explains why the model has no feasible plan.
+    ///
+    /// Products the production constraints need but that can't be made from
+    /// the supplied products are listed first. The constraints are then
+    /// dropped one at a time, and those the model stays infeasible without are
+    /// left out, leaving a minimal set of constraints that conflict with each
+    /// other. If the model is feasible, the diagnosis is empty.
+    pub fn diagnose(&self) -> Result<Diagnosis, SolverError> {
+        self.check_model()?;
+
+        let targets = self
+            .production_constraints
+            .iter()
+            .filter(|(_, bounds)| bounds.min.is_some_and(|min| min > 0.0))
+            .map(|(p, _)| p);
+        let unproducible =
+            diagnosis::find_unproducible(&self.model, self.supply_constraints.keys(), targets);
+
+        let feasible = |conflict: &[ConflictingConstraint]| match self.solve_with(
+            &Objective::Machines,
+            self.relaxed_mode(),
+            &[],
+            &|c| conflict.contains(c),
+            false,
+        ) {
+            Ok(_) => Ok(true),
+            Err(SolverError::Infeasible) => Ok(false),
+            Err(e) => Err(e),
+        };
+
+        let mut conflict = self.constraints();
+        if feasible(&conflict)? {
+            return Ok(Diagnosis::new(unproducible, Vec::new()));
+        }
+        let mut i = 0;
+        while i < conflict.len() {
+            let dropped = conflict.remove(i);
+            if feasible(&conflict)? {
+                conflict.insert(i, dropped);
+                i += 1;
+            }
+        }
+        Ok(Diagnosis::new(unproducible, conflict))
+    }
+
+    /// Every constraint the model is built from, other than the bounds on the
+    /// machine counts, overflow and supply themselves.
+    fn constraints(&self) -> Vec<ConflictingConstraint> {
+        let mut constraints = self
+            .model
+            .products
+            .iter()
+            .map(|p| ConflictingConstraint::Balance(p.key()))
+            .collect::<Vec<_>>();
+
+        let mut outputs = self.production_constraints.iter().collect::<Vec<_>>();
+        outputs.sort_by_key(|(p, _)| p.key());
+        for (p, bounds) in outputs {
+            if let Some(amount) = bounds.min {
+                constraints.push(ConflictingConstraint::MinimumOutput {
+                    product: p.key(),
+                    amount,
+                });
+            }
+            if let Some(amount) = bounds.max {
+                constraints.push(ConflictingConstraint::MaximumOutput {
+                    product: p.key(),
+                    amount,
+                });
+            }
+        }
+
+        let mut supplies = self.supply_constraints.iter().collect::<Vec<_>>();
+        supplies.sort_by_key(|(p, _)| p.key());
+        constraints.extend(supplies.into_iter().map(|(p, amount)| {
+            ConflictingConstraint::SupplyLimit {
+                product: p.key(),
+                amount: *amount,
+            }
+        }));
+
+        let mut pipes = self.pipe_throughputs.iter().collect::<Vec<_>>();
+        pipes.sort_by_key(|(p, _)| p.key());
+        constraints.extend(pipes.into_iter().map(|(p, amount)| {
+            ConflictingConstraint::PipeThroughput {
+                product: p.key(),
+                amount: *amount,
+            }
+        }));
+
+        let mut forbidden = self
+            .disposal_policies
+            .iter()
+            .filter(|(_, policy)| **policy == DisposalPolicy::Forbidden)
+            .map(|(p, _)| p.key())
+            .collect::<Vec<_>>();
+        forbidden.sort();
+        constraints.extend(
+            forbidden
+                .into_iter()
+                .map(ConflictingConstraint::DisposalForbidden),
+        );
+        constraints
+    }
+
+    /// Builds the problem of minimising `objective` in `mode`, while keeping
+    /// each objective in `bounds` at or below its paired value. Only the
+    /// constraints `keep` returns true for are added to it.
+    fn build<'a>(
+        &'a self,
+        recipies: &'a [Recipe],
+        objective: &Objective,
+        mode: SolveMode,
+        bounds: &[(&Objective, f64)],
+        keep: &dyn Fn(&ConflictingConstraint) -> bool,
+    ) -> BuiltProblem<'a> {
+        let mut problem = Problem::new();
+        let groups = self
             .model
             .machines
             .iter()
-            .cartesian_product(self.model.recipies.iter())
-            .map(|(m, r)| {
-                let v = vars.add(variable().integer().min(0).name(format!(
-                    "machines-{}-{}",
-                    m.name(),
-                    r.name()
-                )));
-                ((m, r), v)
+            .cartesian_product(recipies.iter())
+            .filter(|(m, r)| m.can_craft(r))
+            .flat_map(|(m, r)| {
+                self.loadouts_for(m)
+                    .enumerate()
+                    .map(move |(i, l)| (m, r, i, l))
             })
-            .collect::<HashMap<_, _>>();
+            .map(|(m, r, i, l)| {
+                let name = match i {
+                    0 => format!("machines-{}-{}", m.name(), r.name()),
+                    _ => format!("machines-{}-{}-loadout-{}", m.name(), r.name(), i),
+                };
+                let integer = mode == SolveMode::Integer;
+                MachineGroup {
+                    machine: m,
+                    recipe: r,
+                    loadout: l,
+                    variable: problem.add_variable(name, 0.0, f64::INFINITY, integer),
+                }
+            })
+            .collect::<Vec<_>>();
 
         let overflow = self
             .model
             .products
             .iter()
             .map(|p| {
-                let v = vars.add(variable().min(0).name(format!("{}-overflow", p.name())));
-                (p, v)
+                let name = format!("{}-overflow", p.name());
+                (p, problem.add_variable(name, 0.0, f64::INFINITY, false))
             })
             .collect::<HashMap<_, _>>();
 
-        let objective = machines
-            .values()
-            .copied()
-            .fold(Expression::from_other_affine(0u8), |acc, x| acc + x);
-        let mut problem = vars.minimise(&objective).using(default_solver);
+        let supply = self
+            .supply_constraints
+            .iter()
+            .map(|(p, limit)| {
+                let mut max = f64::INFINITY;
+                if keep(&ConflictingConstraint::SupplyLimit {
+                    product: p.key(),
+                    amount: *limit,
+                }) {
+                    max = *limit;
+                }
+                let name = format!("{}-supply", p.name());
+                (p, problem.add_variable(name, 0.0, max, false))
+            })
+            .collect::<HashMap<_, _>>();
 
-        self.model.products.iter().for_each(|p| {
-            let production_rate: Expression = self
-                .model
-                .recipies
-                .iter()
-                .map(|recipe| {
-                    recipe.production_of(p).map_or_else(
-                        || Expression::from_other_affine(0),
-                        |rate| {
-                            self.model
-                                .machines
-                                .iter()
-                                .map(|machine| {
-                                    machine.production_rate()
-                                        * rate
-                                        * (60.0 / recipe.production_time())
-                                        * machines.get(&(machine, recipe)).cloned().unwrap()
-                                })
-                                .fold(Expression::from_other_affine(0), |acc, x| acc + x)
-                        },
-                    )
-                })
-                .sum();
+        let objective = self.objective_expression(objective, &groups, &overflow, &supply);
+        let bound_rows = bounds
+            .iter()
+            .map(|(bound, value)| {
+                let expression = self.objective_expression(bound, &groups, &overflow, &supply);
+                problem.add_constraint(expression, Comparison::LessOrEqual, objective_bound(*value))
+            })
+            .collect();
 
-            let consumption_rate: Expression = self
-                .model
-                .recipies
-                .iter()
-                .map(|recipe| {
-                    recipe.usage_of(p).map_or_else(
-                        || Expression::from_other_affine(0),
-                        |rate| {
-                            self.model
-                                .machines
-                                .iter()
-                                .map(|machine| {
-                                    machine.production_rate()
-                                        * rate
-                                        * (60.0 / recipe.production_time())
-                                        * machines.get(&(machine, recipe)).cloned().unwrap()
-                                })
-                                .fold(Expression::from_other_affine(0), |acc, x| acc + x)
-                        },
-                    )
-                })
-                .sum();
+        let mut balance_rows = HashMap::new();
+        let mut minimum_rows = HashMap::new();
+        let mut maximum_rows = HashMap::new();
+        let mut disposal_rows = HashMap::new();
+
+        self.model.products.iter().for_each(|p| {
+            if !keep(&ConflictingConstraint::Balance(p.key())) {
+                return;
+            }
+            let net_rate = self.net_rate_expression(p, &groups);
 
             let extra = overflow.get(p).map_or_else(
                 || Expression::from_other_affine(0),
                 Expression::from_other_affine,
             );
 
-            problem.add_constraint(constraint!(production_rate - consumption_rate == extra));
-        });
-
-        self.production_constraints.iter().for_each(|(p, v)| {
-            let consumption_rate: Expression = self
-                .model
-                .recipies
-                .iter()
-                .map(|r| (r, r.usage_of(p)))
-                .map(|(recipe, rate)| {
-                    self.model
-                        .machines
-                        .iter()
-                        .map(|machine| {
-                            machine.production_rate()
-                                * rate.unwrap_or(0.0)
-                                * (60.0 / recipe.production_time())
-                                * machines.get(&(machine, recipe)).cloned().unwrap()
-                        })
-                        .fold(Expression::from_other_affine(0), |acc, x| acc + x)
-                })
-                .sum();
-
-            let extra = overflow.get(p).map_or_else(
+            let supplied = supply.get(p).map_or_else(
                 || Expression::from_other_affine(0),
                 Expression::from_other_affine,
             );
 
-            let needed_production = Expression::from_other_affine(*v);
-            problem.add_constraint(constraint!(consumption_rate + extra >= needed_production));
+            let row = problem.add_constraint(net_rate + supplied - extra, Comparison::Equal, 0.0);
+            balance_rows.insert(p.key(), row);
+        });
+
+        self.production_constraints.iter().for_each(|(p, bounds)| {
+            let net_rate = self.net_rate_expression(p, &groups);
+            if let Some(amount) = bounds.min {
+                let product = p.key();
+                if keep(&ConflictingConstraint::MinimumOutput { product, amount }) {
+                    let net_rate = net_rate.clone();
+                    let row = problem.add_constraint(net_rate, Comparison::GreaterOrEqual, amount);
+                    minimum_rows.insert(p.key(), row);
+                }
+            }
+            if let Some(amount) = bounds.max {
+                let product = p.key();
+                if keep(&ConflictingConstraint::MaximumOutput { product, amount }) {
+                    let row = problem.add_constraint(net_rate, Comparison::LessOrEqual, amount);
+                    maximum_rows.insert(p.key(), row);
+                }
+            }
         });
 
-        Ok(problem.solve()?.eval(objective))
+        self.pipe_throughputs
+            .iter()
+            .filter(|(p, amount)| {
+                keep(&ConflictingConstraint::PipeThroughput {
+                    product: p.key(),
+                    amount: **amount,
+                })
+            })
+            .for_each(|(p, amount)| {
+                let supplied = supply.get(p).map_or_else(
+                    || Expression::from_other_affine(0),
+                    Expression::from_other_affine,
+                );
+                let carried = self.production_rate_expression(p, &groups) + supplied;
+                problem.add_constraint(carried, Comparison::LessOrEqual, *amount);
+            });
+
+        self.model
+            .products
+            .iter()
+            .filter(|p| self.disposal_policy(p) == DisposalPolicy::Forbidden)
+            .filter(|p| keep(&ConflictingConstraint::DisposalForbidden(p.key())))
+            .for_each(|p| {
+                let extra = Expression::from_other_affine(overflow[p]);
+                let row =
+                    problem.add_constraint(extra, Comparison::LessOrEqual, self.minimum_output(p));
+                disposal_rows.insert(p.key(), row);
+            });
+
+        BuiltProblem {
+            problem,
+            objective,
+            groups,
+            overflow,
+            supply,
+            bound_rows,
+            balance_rows,
+            minimum_rows,
+            maximum_rows,
+            disposal_rows,
+        }
+    }
+
+    /// Reads the plan `mode` asks for off a `solution` to `built`, which
+    /// minimised `goal`.
+    fn plan_from(
+        &self,
+        built: &BuiltProblem,
+        solution: &HashMap<Variable, f64>,
+        goal: &Objective,
+        mode: SolveMode,
+    ) -> ProductionPlan {
+        let BuiltProblem {
+            objective,
+            groups,
+            overflow,
+            supply,
+            ..
+        } = built;
+
+        // Rounded up machines craft only as often as the fractional ones did,
+        // so the extra machines sit idle for the rest of the time: they draw
+        // their idle power, but emit no pollution.
+        let rounded = |count: f64| match mode {
+            SolveMode::RoundedUp => (count - ROUNDING_TOLERANCE).ceil().max(0.0),
+            SolveMode::Integer | SolveMode::Continuous => count,
+        };
+
+        let mut recipe_groups = groups
+            .iter()
+            .map(|g| (g, solution.value(g.variable)))
+            .filter(|(_, count)| rounded(*count) > 0.0)
+            .map(|(g, count)| {
+                let idle = rounded(count) - count;
+                RecipeGroup::new(
+                    g.machine.name().to_owned(),
+                    g.recipe.name().to_owned(),
+                    g.loadout.clone(),
+                    rounded(count),
+                    g.power() * count + g.idle_power() * idle,
+                    g.pollution() * count,
+                    g.recipe
+                        .usage()
+                        .keys()
+                        .chain(g.recipe.production().keys())
+                        .map(|p| {
+                            let rate = (g.production_of(p) - g.usage_of(p)) * count;
+                            (p.key(), rate)
+                        })
+                        .collect(),
+                )
+            })
+            .collect::<Vec<_>>();
+        recipe_groups.sort_by(|a, b| (a.machine(), a.recipe()).cmp(&(b.machine(), b.recipe())));
+
+        let overflow = overflow
+            .iter()
+            .map(|(p, v)| (p.key(), solution.value(*v)))
+            .collect();
+
+        let supply = supply
+            .iter()
+            .map(|(p, v)| (p.key(), solution.value(*v)))
+            .collect();
+
+        let net_rates = self
+            .model
+            .products
+            .iter()
+            .map(|p| {
+                let rate = groups
+                    .iter()
+                    .map(|g| (g.production_of(p) - g.usage_of(p)) * solution.value(g.variable))
+                    .sum();
+                (p.key(), rate)
+            })
+            .collect();
+
+        let idle_cost = groups
+            .iter()
+            .map(|g| {
+                let count = solution.value(g.variable);
+                (rounded(count) - count) * self.idle_machine_cost(goal, g)
+            })
+            .sum::<f64>();
+
+        ProductionPlan::new(
+            solution.eval(objective) + idle_cost,
+            recipe_groups,
+            overflow,
+            supply,
+            net_rates,
+        )
+    }
+
+    /// Solves the model minimising `objective` in `mode`, while keeping each
+    /// objective in `bounds` at or below its paired value. If `analyse` is
+    /// set, the plan comes with its [`Sensitivity`], which only makes sense
+    /// for continuous machine counts.
+    ///
+    /// Only the constraints `keep` returns true for are added to the problem.
+    fn solve_with(
+        &self,
+        objective: &Objective,
+        mode: SolveMode,
+        bounds: &[(&Objective, f64)],
+        keep: &dyn Fn(&ConflictingConstraint) -> bool,
+        analyse: bool,
+    ) -> Result<ProductionPlan, SolverError> {
+        let recipies = self.model.recipe_variants();
+        let built = self.build(&recipies, objective, mode, bounds, keep);
+        let solution = built.problem.minimise(&built.objective, self.backend)?;
+        let plan = self.plan_from(&built, &solution, objective, mode);
+        if !analyse {
+            return Ok(plan);
+        }
+
+        let BuiltProblem {
+            problem,
+            objective,
+            groups,
+            balance_rows,
+            minimum_rows,
+            maximum_rows,
+            ..
+        } = built;
+        let costs = problem.dual_values(&objective, self.backend)?;
+        let prices = |rows: HashMap<String, usize>| {
+            rows.into_iter()
+                .map(|(product, row)| (product, costs[row]))
+                .collect()
+        };
+        let reduced_costs = groups
+            .iter()
+            .map(|g| {
+                ReducedCost::new(
+                    g.machine.name().to_owned(),
+                    g.recipe.name().to_owned(),
+                    g.loadout.clone(),
+                    problem.reduced_cost(&objective, &costs, g.variable),
+                )
+            })
+            .collect();
+        Ok(plan.with_sensitivity(Sensitivity::new(
+            prices(balance_rows),
+            prices(minimum_rows),
+            prices(maximum_rows),
+            reduced_costs,
+        )))
     }
 }
@@ -0,0 +1,190 @@
+use std::fmt::{Display, Formatter};
+
+use serde::Deserialize;
+use serde::Serialize;
+
+/// How serious a problem with a model is.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    /// The model can still be solved, but probably doesn't mean what its
+    /// author intended.
+    Warning,
+    /// The model can't be solved until the problem is fixed.
+    Error,
+}
+
+/// A single problem found while validating a model.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum Issue {
+    /// Two recipes share a name, so one silently replaces the other.
+    DuplicateRecipe(String),
+    /// Two products share a name.
+    DuplicateProduct(String),
+    /// Two machines share a name.
+    DuplicateMachine(String),
+    /// A recipe uses or produces a product that isn't part of the model.
+    UnknownProduct { recipe: String, product: String },
+    /// A recipe takes no time, a negative amount of time, or an infinite one.
+    InvalidProductionTime {
+        recipe: String,
+        production_time: f64,
+    },
+    /// A recipe uses or produces a negative or non-finite amount of a product.
+    InvalidAmount {
+        recipe: String,
+        product: String,
+        amount: f64,
+    },
+    /// A recipe yields a product over a range that is out of order, or with a
+    /// probability that isn't above 0 and at most 1.
+    InvalidOutput { recipe: String, product: String },
+    /// A machine crafts at a speed of zero or less, or an infinite one.
+    InvalidProductionRate {
+        machine: String,
+        production_rate: f64,
+    },
+    /// A recipe uses or produces none of a product.
+    ZeroAmount { recipe: String, product: String },
+    /// A recipe produces nothing at all.
+    EmptyRecipe(String),
+    /// No machine can run a recipe.
+    UncraftableRecipe(String),
+    /// No recipe uses or produces a product.
+    UnusedProduct(String),
+    /// A recipe accepts a fluid over a range of temperatures, but no product
+    /// of that fluid falls within the range.
+    NoFluidInRange { recipe: String, fluid: String },
+}
+
+impl Issue {
+    pub fn severity(&self) -> Severity {
+        match self {
+            Issue::DuplicateRecipe(_)
+            | Issue::DuplicateProduct(_)
+            | Issue::DuplicateMachine(_)
+            | Issue::UnknownProduct { .. }
+            | Issue::InvalidProductionTime { .. }
+            | Issue::InvalidAmount { .. }
+            | Issue::InvalidOutput { .. }
+            | Issue::InvalidProductionRate { .. } => Severity::Error,
+            Issue::ZeroAmount { .. }
+            | Issue::EmptyRecipe(_)
+            | Issue::UncraftableRecipe(_)
+            | Issue::UnusedProduct(_)
+            | Issue::NoFluidInRange { .. } => Severity::Warning,
+        }
+    }
+}
+
+impl Display for Issue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Issue::DuplicateRecipe(recipe) => write!(f, "recipe `{}` is defined twice", recipe),
+            Issue::DuplicateProduct(product) => {
+                write!(f, "product `{}` is defined twice", product)
+            }
+            Issue::DuplicateMachine(machine) => {
+                write!(f, "machine `{}` is defined twice", machine)
+            }
+            Issue::UnknownProduct { recipe, product } => write!(
+                f,
+                "recipe `{}` refers to product `{}`, which is not part of the model",
+                recipe, product
+            ),
+            Issue::InvalidProductionTime {
+                recipe,
+                production_time,
+            } => write!(
+                f,
+                "recipe `{}` has an invalid production time of {}s",
+                recipe, production_time
+            ),
+            Issue::InvalidAmount {
+                recipe,
+                product,
+                amount,
+            } => write!(
+                f,
+                "recipe `{}` has an invalid amount {} of product `{}`",
+                recipe, amount, product
+            ),
+            Issue::InvalidOutput { recipe, product } => write!(
+                f,
+                "recipe `{}` has an invalid range or probability for product `{}`",
+                recipe, product
+            ),
+            Issue::InvalidProductionRate {
+                machine,
+                production_rate,
+            } => write!(
+                f,
+                "machine `{}` has an invalid production rate of {}",
+                machine, production_rate
+            ),
+            Issue::ZeroAmount { recipe, product } => write!(
+                f,
+                "recipe `{}` lists product `{}` with an amount of 0",
+                recipe, product
+            ),
+            Issue::EmptyRecipe(recipe) => write!(f, "recipe `{}` produces nothing", recipe),
+            Issue::UncraftableRecipe(recipe) => {
+                write!(f, "no machine can run recipe `{}`", recipe)
+            }
+            Issue::UnusedProduct(product) => {
+                write!(f, "no recipe uses or produces product `{}`", product)
+            }
+            Issue::NoFluidInRange { recipe, fluid } => write!(
+                f,
+                "recipe `{}` accepts no `{}` of any temperature in the model",
+                recipe, fluid
+            ),
+        }
+    }
+}
+
+/// Everything found wrong with a model, in the order it was found.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Validation {
+    issues: Vec<Issue>,
+}
+
+impl Validation {
+    pub fn new(issues: Vec<Issue>) -> Self {
+        Self { issues }
+    }
+
+    pub fn issues(&self) -> &[Issue] {
+        &self.issues
+    }
+
+    pub fn errors(&self) -> impl Iterator<Item = &Issue> {
+        self.issues
+            .iter()
+            .filter(|i| i.severity() == Severity::Error)
+    }
+
+    pub fn warnings(&self) -> impl Iterator<Item = &Issue> {
+        self.issues
+            .iter()
+            .filter(|i| i.severity() == Severity::Warning)
+    }
+
+    /// Whether the model can be solved, i.e. nothing worse than a warning was
+    /// found.
+    pub fn is_valid(&self) -> bool {
+        self.errors().next().is_none()
+    }
+}
+
+impl Display for Validation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for issue in &self.issues {
+            let severity = match issue.severity() {
+                Severity::Warning => "warning",
+                Severity::Error => "error",
+            };
+            writeln!(f, "{}: {}", severity, issue)?;
+        }
+        Ok(())
+    }
+}
@@ -1,10 +1,78 @@
 use std::collections::HashMap;
+use std::path::PathBuf;
 
 use factorio_optimizer::{
-    factorio::{Machine, Product, Recipe},
-    solver::{Model, Solver},
+    backend::SolverBackend,
+    cli,
+    diagnosis::{ConflictingConstraint, Reason},
+    dump::{self, Difficulty},
+    error::SolverError,
+    factorio::{
+        Beacon, BeaconSetup, Effect, Loadout, Machine, Module, Output, Product, Recipe,
+        TemperatureRange,
+    },
+    graph,
+    model_file::{self, ModelFormat},
+    objective::Objective,
+    plan::{ProductionPlan, RecipeGroup},
+    science::{Labs, SciencePack, ScienceTarget, RESEARCH},
+    solver::{DisposalPolicy, Model, OutputConstraint, SolveMode, Solver},
+    validation::Issue,
 };
 
+/// A file in the temporary directory named after this test run, so
+/// overlapping runs don't clobber each other's files.
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!(
+        "factorio-optimizer-{}-{}",
+        std::process::id(),
+        name
+    ))
+}
+
+/// Whether `program` can be found on the `PATH`.
+fn on_path(program: &str) -> bool {
+    std::env::var_os("PATH")
+        .is_some_and(|path| std::env::split_paths(&path).any(|dir| dir.join(program).is_file()))
+}
+
+/// Electronic circuits made by `machines` out of copper cable and supplied
+/// iron and copper plates, 60 of them a minute. Returns the solver along with
+/// its products, iron, copper, cable and circuits, and its cable and circuit
+/// recipes.
+fn circuit_solver(machines: Vec<Machine>) -> (Solver, [Product; 4], Vec<Recipe>) {
+    let iron = Product::new("Iron plate".to_owned());
+    let copper = Product::new("Copper plate".to_owned());
+    let cable = Product::new("Copper cable".to_owned());
+    let circuit = Product::new("Electronic circuit".to_owned());
+    let recipies = vec![
+        Recipe::new(
+            "Copper cable".to_owned(),
+            0.5,
+            HashMap::from([(copper.clone(), 1.0)]),
+            HashMap::from([(cable.clone(), 2.0)]),
+        ),
+        Recipe::new(
+            "Electronic circuit".to_owned(),
+            0.5,
+            HashMap::from([(iron.clone(), 1.0), (cable.clone(), 3.0)]),
+            HashMap::from([(circuit.clone(), 1.0)]),
+        ),
+    ];
+    let products = [iron, copper, cable, circuit];
+    let model = Model::new(recipies.clone(), products.to_vec(), machines);
+    let mut solver = Solver::new(model);
+    for supplied in &products[..2] {
+        solver
+            .add_supply_constraint(supplied.clone(), 1000.0)
+            .unwrap();
+    }
+    solver
+        .add_production_constraint(products[3].clone(), 60.0)
+        .unwrap();
+    (solver, products, recipies)
+}
+
 #[test]
 fn coal_production() {
     let machines = vec![Machine::new("Electric mining drill".to_owned(), 0.5)];
@@ -17,7 +85,2142 @@ fn coal_production() {
     )];
 
     let mut solver = Solver::new(Model::new(recipies, products, machines));
-    solver.add_production_constraint(Product::new("Coal".to_owned()), 30.0);
+    solver
+        .add_production_constraint(Product::new("Coal".to_owned()), 30.0)
+        .unwrap();
+
+    assert_eq!(solver.solve().unwrap().objective(), 1.0);
+}
+
+#[test]
+fn production_plan_reports_machines_and_rates() {
+    let machines = vec![Machine::new("Assembling machine 1".to_owned(), 0.5)];
+    let products = vec![
+        Product::new("Iron plate".to_owned()),
+        Product::new("Iron gear wheel".to_owned()),
+    ];
+    let recipies = vec![
+        Recipe::new(
+            "Iron plate".to_owned(),
+            1.0,
+            HashMap::new(),
+            HashMap::from([(products[0].clone(), 1.0)]),
+        ),
+        Recipe::new(
+            "Iron gear wheel".to_owned(),
+            0.5,
+            HashMap::from([(products[0].clone(), 2.0)]),
+            HashMap::from([(products[1].clone(), 1.0)]),
+        ),
+    ];
+
+    let mut solver = Solver::new(Model::new(
+        recipies.clone(),
+        products.clone(),
+        machines.clone(),
+    ));
+    solver
+        .add_production_constraint(products[1].clone(), 60.0)
+        .unwrap();
+
+    let plan = solver.solve().unwrap();
+    assert_eq!(plan.machine_count(&machines[0], &recipies[1]), 1.0);
+    assert_eq!(plan.machine_count(&machines[0], &recipies[0]), 4.0);
+    assert_eq!(plan.total_machines(), 5.0);
+    assert_eq!(plan.net_rate_of(&products[0]), 0.0);
+    assert_eq!(plan.net_rate_of(&products[1]), 60.0);
+    assert_eq!(plan.overflow_of(&products[1]), 60.0);
+
+    let json = serde_json::to_string(&plan).unwrap();
+    assert!(json.contains("Iron gear wheel"));
+}
+
+#[test]
+fn machines_only_run_recipes_in_their_categories() {
+    let machines = vec![
+        Machine::new("Electric mining drill".to_owned(), 1.0)
+            .with_crafting_categories(["basic-solid"]),
+        Machine::new("Assembling machine 1".to_owned(), 0.5),
+    ];
+    let products = vec![
+        Product::new("Iron ore".to_owned()),
+        Product::new("Iron gear wheel".to_owned()),
+    ];
+    let recipies = vec![
+        Recipe::new(
+            "Iron ore".to_owned(),
+            1.0,
+            HashMap::new(),
+            HashMap::from([(products[0].clone(), 1.0)]),
+        )
+        .with_category("basic-solid".to_owned()),
+        Recipe::new(
+            "Iron gear wheel".to_owned(),
+            0.5,
+            HashMap::from([(products[0].clone(), 2.0)]),
+            HashMap::from([(products[1].clone(), 1.0)]),
+        ),
+    ];
+
+    let mut solver = Solver::new(Model::new(
+        recipies.clone(),
+        products.clone(),
+        machines.clone(),
+    ));
+    solver
+        .add_production_constraint(products[1].clone(), 60.0)
+        .unwrap();
+
+    let plan = solver.solve().unwrap();
+    assert_eq!(plan.machine_count(&machines[0], &recipies[0]), 2.0);
+    assert_eq!(plan.machine_count(&machines[0], &recipies[1]), 0.0);
+    assert_eq!(plan.machine_count(&machines[1], &recipies[0]), 0.0);
+    assert_eq!(plan.machine_count(&machines[1], &recipies[1]), 1.0);
+}
+
+#[test]
+fn import_data_raw_dump() {
+    let json = r#"{
+        "recipe": {
+            "iron-gear-wheel": {
+                "type": "recipe",
+                "name": "iron-gear-wheel",
+                "normal": {
+                    "ingredients": [["iron-plate", 2]],
+                    "result": "iron-gear-wheel"
+                },
+                "expensive": {
+                    "ingredients": [["iron-plate", 4]],
+                    "result": "iron-gear-wheel"
+                }
+            },
+            "iron-plate": {
+                "type": "recipe",
+                "name": "iron-plate",
+                "category": "smelting",
+                "energy_required": 3.2,
+                "ingredients": [{"type": "item", "name": "iron-ore", "amount": 1}],
+                "result": "iron-plate"
+            },
+            "basic-oil-processing": {
+                "type": "recipe",
+                "name": "basic-oil-processing",
+                "category": "oil-processing",
+                "energy_required": 5,
+                "ingredients": [{"type": "fluid", "name": "crude-oil", "amount": 100}],
+                "results": [{"type": "fluid", "name": "petroleum-gas", "amount": 45}]
+            }
+        },
+        "assembling-machine": {
+            "assembling-machine-2": {
+                "name": "assembling-machine-2",
+                "crafting_speed": 0.75,
+                "crafting_categories": ["crafting", "advanced-crafting"],
+                "energy_usage": "150kW",
+                "energy_source": {
+                    "type": "electric",
+                    "usage_priority": "secondary-input",
+                    "emissions_per_minute": 3
+                },
+                "collision_box": [[-1.2, -1.2], [1.2, 1.2]]
+            }
+        },
+        "furnace": {
+            "stone-furnace": {
+                "name": "stone-furnace",
+                "crafting_speed": 1,
+                "crafting_categories": ["smelting"],
+                "energy_usage": "90kW",
+                "energy_source": {"type": "burner", "fuel_category": "chemical"}
+            }
+        },
+        "mining-drill": {
+            "electric-mining-drill": {
+                "name": "electric-mining-drill",
+                "mining_speed": 0.5,
+                "resource_categories": ["basic-solid"],
+                "energy_usage": "90kW",
+                "energy_source": {"type": "electric"}
+            }
+        },
+        "resource": {
+            "iron-ore": {
+                "name": "iron-ore",
+                "minable": {"mining_time": 1, "result": "iron-ore"}
+            }
+        }
+    }"#;
+
+    let model = dump::parse_model(json, Difficulty::Normal).unwrap();
+    assert_eq!(model.recipies().len(), 4);
+    assert_eq!(model.machines().len(), 3);
+    assert_eq!(model.products().len(), 5);
+    assert_eq!(
+        model
+            .products()
+            .iter()
+            .filter(|p| p.is_fluid())
+            .map(Product::name)
+            .collect::<Vec<_>>(),
+        ["crude-oil", "petroleum-gas"]
+    );
+
+    let gears = model
+        .recipies()
+        .iter()
+        .find(|r| r.name() == "iron-gear-wheel")
+        .unwrap();
+    assert_eq!(gears.production_time(), 0.5);
+    assert_eq!(
+        gears.usage_of(&Product::new("iron-plate".to_owned())),
+        Some(2.0)
+    );
+
+    let expensive = dump::parse_model(json, Difficulty::Expensive).unwrap();
+    let gears = expensive
+        .recipies()
+        .iter()
+        .find(|r| r.name() == "iron-gear-wheel")
+        .unwrap();
+    assert_eq!(
+        gears.usage_of(&Product::new("iron-plate".to_owned())),
+        Some(4.0)
+    );
+
+    let mut solver = Solver::new(model);
+    solver
+        .add_production_constraint(Product::new("iron-gear-wheel".to_owned()), 90.0)
+        .unwrap();
+    let plan = solver.solve().unwrap();
+    let groups = plan
+        .groups()
+        .iter()
+        .map(|g| (g.machine(), g.recipe(), g.count()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        groups,
+        vec![
+            ("assembling-machine-2", "iron-gear-wheel", 1.0),
+            ("electric-mining-drill", "mining-iron-ore", 7.0),
+            ("stone-furnace", "iron-plate", 10.0),
+        ]
+    );
+    // One assembler drawing 150kW plus a 5kW drain, seven drills at 90kW and
+    // burner furnaces that need no electricity.
+    assert_eq!(plan.power(), 155.0 + 7.0 * 90.0);
+    assert_eq!(plan.pollution(), 3.0);
+}
+
+#[test]
+fn modules_and_beacons_scale_machine_output() {
+    let productivity = Module::new(
+        "Productivity module".to_owned(),
+        Effect::new(-0.25, 0.25, 0.4, 0.05),
+    );
+    let speed = Module::new("Speed module".to_owned(), Effect::new(0.5, 0.0, 0.5, 0.0));
+    let beacon = Beacon::new("Beacon".to_owned(), 0.5, 2);
+    let loadout = Loadout::new(
+        vec![productivity.clone(), productivity],
+        vec![BeaconSetup::new(beacon, 2, vec![speed])],
+    );
+
+    let machines = vec![
+        Machine::new("Assembling machine".to_owned(), 1.0)
+            .with_module_slots(2)
+            .with_loadout(loadout),
+        Machine::new("Furnace".to_owned(), 1.0).with_crafting_categories(["smelting"]),
+    ];
+    assert_eq!(machines[0].crafting_speed(), 1.0);
+    assert_eq!(machines[0].productivity(), 1.5);
+
+    let products = vec![
+        Product::new("Iron plate".to_owned()),
+        Product::new("Iron gear wheel".to_owned()),
+    ];
+    let recipies = vec![
+        Recipe::new(
+            "Iron plate".to_owned(),
+            1.0,
+            HashMap::new(),
+            HashMap::from([(products[0].clone(), 1.0)]),
+        )
+        .with_category("smelting".to_owned()),
+        Recipe::new(
+            "Iron gear wheel".to_owned(),
+            0.5,
+            HashMap::from([(products[0].clone(), 2.0)]),
+            HashMap::from([(products[1].clone(), 1.0)]),
+        ),
+    ];
+
+    let mut solver = Solver::new(Model::new(
+        recipies.clone(),
+        products.clone(),
+        machines.clone(),
+    ));
+    solver
+        .add_production_constraint(products[1].clone(), 180.0)
+        .unwrap();
+
+    // Productivity only boosts output: one assembler makes 180 gears/min
+    // from 240 plates/min rather than 360.
+    let plan = solver.solve().unwrap();
+    assert_eq!(plan.machine_count(&machines[0], &recipies[1]), 1.0);
+    assert_eq!(plan.machine_count(&machines[1], &recipies[0]), 4.0);
+    assert_eq!(plan.net_rate_of(&products[0]), 0.0);
+}
+
+#[test]
+fn solver_chooses_loadouts() {
+    let speed = Module::new("Speed module".to_owned(), Effect::new(0.5, 0.0, 0.5, 0.0));
+    let machines = vec![Machine::new("Assembling machine".to_owned(), 1.0).with_module_slots(4)];
+    let products = vec![Product::new("Iron gear wheel".to_owned())];
+    let recipies = vec![Recipe::new(
+        "Iron gear wheel".to_owned(),
+        0.5,
+        HashMap::new(),
+        HashMap::from([(products[0].clone(), 1.0)]),
+    )];
+    let fast = Loadout::new(vec![speed; 4], Vec::new());
+
+    let mut solver = Solver::new(Model::new(
+        recipies.clone(),
+        products.clone(),
+        machines.clone(),
+    ));
+    solver.add_loadout(fast.clone());
+    solver
+        .add_production_constraint(products[0].clone(), 240.0)
+        .unwrap();
+
+    let plan = solver.solve().unwrap();
+    assert_eq!(plan.total_machines(), 1.0);
+    assert_eq!(plan.groups()[0].loadout(), &fast);
+
+    // Once modules cost half a machine each, two plain assemblers are cheaper.
+    solver.set_module_cost(0.5);
+    let plan = solver.solve().unwrap();
+    assert_eq!(plan.total_machines(), 2.0);
+    assert_eq!(plan.groups()[0].loadout(), &Loadout::default());
+}
+
+#[test]
+fn minimise_power() {
+    let machines = vec![
+        Machine::new("Assembling machine 2".to_owned(), 0.75).with_power(150.0, 5.0),
+        Machine::new("Assembling machine 3".to_owned(), 1.25).with_power(375.0, 12.5),
+    ];
+    let products = vec![Product::new("Iron gear wheel".to_owned())];
+    let recipies = vec![Recipe::new(
+        "Iron gear wheel".to_owned(),
+        0.5,
+        HashMap::new(),
+        HashMap::from([(products[0].clone(), 1.0)]),
+    )];
+
+    let mut solver = Solver::new(Model::new(
+        recipies.clone(),
+        products.clone(),
+        machines.clone(),
+    ));
+    solver
+        .add_production_constraint(products[0].clone(), 150.0)
+        .unwrap();
+
+    let plan = solver.solve().unwrap();
+    assert_eq!(plan.machine_count(&machines[1], &recipies[0]), 1.0);
+    assert_eq!(plan.power(), 387.5);
+
+    solver.set_objective(Objective::Power);
+    let plan = solver.solve().unwrap();
+    assert_eq!(plan.objective(), 310.0);
+    assert_eq!(plan.machine_count(&machines[0], &recipies[0]), 2.0);
+    assert_eq!(plan.power(), 310.0);
+
+    // Beacons draw power whether or not the machines they reach are busy,
+    // split between every machine they reach.
+    let speed = Module::new("Speed module".to_owned(), Effect::new(0.5, 0.0, 0.5, 0.0));
+    let beacon = Beacon::new("Beacon".to_owned(), 0.5, 2).with_power(480.0);
+    let beaconed = Loadout::new(
+        Vec::new(),
+        vec![BeaconSetup::new(beacon, 1, vec![speed.clone(), speed]).with_shared_by(4)],
+    );
+    assert_eq!(beaconed.beacon_power(), 120.0);
+    assert_eq!(machines[0].idle_power_with(&beaconed), 125.0);
+    assert_eq!(
+        machines[0].active_power_with(&beaconed),
+        150.0 * 1.5 + 125.0
+    );
+}
+
+#[test]
+fn pluggable_objectives() {
+    let machines = vec![
+        Machine::new("Assembling machine 2".to_owned(), 0.75)
+            .with_power(150.0, 5.0)
+            .with_emissions(3.0)
+            .with_size(3, 3),
+        Machine::new("Assembling machine 3".to_owned(), 1.25)
+            .with_power(375.0, 12.5)
+            .with_emissions(2.0)
+            .with_size(3, 3),
+        Machine::new("Big assembler".to_owned(), 2.5)
+            .with_power(1000.0, 0.0)
+            .with_emissions(4.0)
+            .with_size(5, 5),
+    ];
+    let products = vec![
+        Product::new("Iron plate".to_owned()),
+        Product::new("Iron gear wheel".to_owned()),
+    ];
+    let recipies = vec![
+        Recipe::new(
+            "Iron plate".to_owned(),
+            1.0,
+            HashMap::new(),
+            HashMap::from([(products[0].clone(), 1.0)]),
+        )
+        .with_category("smelting".to_owned()),
+        Recipe::new(
+            "Iron gear wheel".to_owned(),
+            0.5,
+            HashMap::new(),
+            HashMap::from([(products[1].clone(), 1.0)]),
+        ),
+    ];
+
+    let mut solver = Solver::new(Model::new(
+        recipies.clone(),
+        products.clone(),
+        machines.clone(),
+    ));
+    solver
+        .add_production_constraint(products[1].clone(), 300.0)
+        .unwrap();
+
+    // A single big assembler is the fewest machines.
+    let plan = solver.solve().unwrap();
+    assert_eq!(plan.objective(), 1.0);
+
+    solver.set_objective(Objective::Area);
+    assert_eq!(solver.solve().unwrap().objective(), 18.0);
+
+    solver.set_objective(Objective::Pollution);
+    assert_eq!(solver.solve().unwrap().objective(), 4.0);
+
+    solver.set_objective(Objective::Weighted(vec![
+        (1.0, Objective::Machines),
+        (0.01, Objective::Power),
+    ]));
+    let plan = solver.solve().unwrap();
+    assert_eq!(plan.machine_count(&machines[1], &recipies[1]), 2.0);
+
+    // Fewest machines first, then the least power among those plans.
+    solver.set_objective(Objective::Lexicographic(vec![
+        Objective::Machines,
+        Objective::Power,
+    ]));
+    let plan = solver.solve().unwrap();
+    assert_eq!(plan.machine_count(&machines[2], &recipies[1]), 1.0);
+    assert_eq!(plan.objective(), 1000.0);
+
+    solver.set_objective(Objective::RawResources(vec![products[0].clone()]));
+    let plan = solver.solve().unwrap();
+    assert_eq!(plan.objective(), 0.0);
+}
+
+#[test]
+fn maximise_output_from_limited_supply() {
+    let machines = vec![
+        Machine::new("Stone furnace".to_owned(), 1.0).with_crafting_categories(["smelting"]),
+        Machine::new("Assembling machine".to_owned(), 1.0),
+    ];
+    let products = vec![
+        Product::new("Iron ore".to_owned()),
+        Product::new("Iron plate".to_owned()),
+        Product::new("Iron gear wheel".to_owned()),
+    ];
+    let recipies = vec![
+        Recipe::new(
+            "Iron plate".to_owned(),
+            1.0,
+            HashMap::from([(products[0].clone(), 1.0)]),
+            HashMap::from([(products[1].clone(), 1.0)]),
+        )
+        .with_category("smelting".to_owned()),
+        Recipe::new(
+            "Iron gear wheel".to_owned(),
+            0.5,
+            HashMap::from([(products[1].clone(), 2.0)]),
+            HashMap::from([(products[2].clone(), 1.0)]),
+        ),
+    ];
+
+    let mut solver = Solver::new(Model::new(
+        recipies.clone(),
+        products.clone(),
+        machines.clone(),
+    ));
+    solver
+        .add_supply_constraint(products[0].clone(), 480.0)
+        .unwrap();
+    solver.set_objective(Objective::Lexicographic(vec![
+        Objective::MaximiseOutput(vec![(products[2].clone(), 1.0)]),
+        Objective::Machines,
+    ]));
+
+    // 480 ore/min smelts into 480 plates/min in 8 furnaces, which two
+    // assemblers turn into 240 gears/min.
+    let plan = solver.solve().unwrap();
+    assert_eq!(plan.supply_of(&products[0]), 480.0);
+    assert_eq!(plan.machine_count(&machines[0], &recipies[0]), 8.0);
+    assert_eq!(plan.machine_count(&machines[1], &recipies[1]), 2.0);
+    assert_eq!(plan.net_rate_of(&products[2]), 240.0);
+    assert_eq!(plan.net_rate_of(&products[0]), -480.0);
+
+    // Supplied ore counts as raw resources too, so smelting 120 plates/min in
+    // two furnaces beats one furnace wasting five ore per plate.
+    let wasteful = Recipe::new(
+        "Wasteful iron plate".to_owned(),
+        0.5,
+        HashMap::from([(products[0].clone(), 5.0)]),
+        HashMap::from([(products[1].clone(), 1.0)]),
+    )
+    .with_category("smelting".to_owned());
+    let mut recipies = recipies;
+    recipies.push(wasteful);
+    let mut solver = Solver::new(Model::new(
+        recipies.clone(),
+        products.clone(),
+        machines.clone(),
+    ));
+    solver
+        .add_supply_constraint(products[0].clone(), 1000.0)
+        .unwrap();
+    solver
+        .add_production_constraint(products[1].clone(), 120.0)
+        .unwrap();
+    let raw = Objective::RawResources(vec![products[0].clone()]);
+    solver.set_objective(raw.clone());
+    assert_eq!(solver.solve().unwrap().objective(), 120.0);
+    solver.set_objective(Objective::Lexicographic(vec![raw, Objective::Machines]));
+    let plan = solver.solve().unwrap();
+    assert!((plan.supply_of(&products[0]) - 120.0).abs() < 1e-3);
+    assert_eq!(plan.machine_count(&machines[0], &recipies[2]), 0.0);
+}
+
+#[test]
+fn production_constraints_count_net_output() {
+    let machines = vec![
+        Machine::new("Stone furnace".to_owned(), 1.0).with_crafting_categories(["smelting"]),
+        Machine::new("Assembling machine 1".to_owned(), 0.5),
+    ];
+    let products = vec![
+        Product::new("Iron plate".to_owned()),
+        Product::new("Iron gear wheel".to_owned()),
+    ];
+    let recipies = vec![
+        Recipe::new(
+            "Iron plate".to_owned(),
+            1.0,
+            HashMap::new(),
+            HashMap::from([(products[0].clone(), 1.0)]),
+        )
+        .with_category("smelting".to_owned()),
+        Recipe::new(
+            "Iron gear wheel".to_owned(),
+            0.5,
+            HashMap::from([(products[0].clone(), 2.0)]),
+            HashMap::from([(products[1].clone(), 1.0)]),
+        ),
+    ];
+
+    let mut solver = Solver::new(Model::new(
+        recipies.clone(),
+        products.clone(),
+        machines.clone(),
+    ));
+    solver
+        .add_production_constraint(products[1].clone(), 60.0)
+        .unwrap();
+    solver
+        .add_production_constraint(products[0].clone(), 60.0)
+        .unwrap();
+
+    // Plates consumed by the gear assembler don't count towards the 60
+    // plates/min leaving the factory.
+    let plan = solver.solve().unwrap();
+    assert_eq!(plan.machine_count(&machines[0], &recipies[0]), 3.0);
+    assert_eq!(plan.machine_count(&machines[1], &recipies[1]), 1.0);
+    assert_eq!(plan.net_rate_of(&products[0]), 60.0);
+    assert_eq!(plan.net_rate_of(&products[1]), 60.0);
+}
+
+#[test]
+fn exact_and_maximum_output_constraints() {
+    let machines = vec![
+        Machine::new("Oil refinery".to_owned(), 1.0).with_crafting_categories(["oil-processing"]),
+        Machine::new("Chemical plant".to_owned(), 0.5).with_crafting_categories(["chemistry"]),
+    ];
+    let products = vec![
+        Product::new("Heavy oil".to_owned()),
+        Product::new("Light oil".to_owned()),
+    ];
+    let recipies = vec![
+        Recipe::new(
+            "Oil processing".to_owned(),
+            1.0,
+            HashMap::new(),
+            HashMap::from([(products[0].clone(), 1.0), (products[1].clone(), 1.0)]),
+        )
+        .with_category("oil-processing".to_owned()),
+        Recipe::new(
+            "Heavy oil cracking".to_owned(),
+            1.0,
+            HashMap::from([(products[0].clone(), 1.0)]),
+            HashMap::from([(products[1].clone(), 1.0)]),
+        )
+        .with_category("chemistry".to_owned()),
+    ];
+
+    let mut solver = Solver::new(Model::new(
+        recipies.clone(),
+        products.clone(),
+        machines.clone(),
+    ));
+    solver
+        .add_production_constraint(products[1].clone(), 120.0)
+        .unwrap();
+
+    let plan = solver.solve().unwrap();
+    assert_eq!(plan.machine_count(&machines[0], &recipies[0]), 2.0);
+    assert_eq!(plan.machine_count(&machines[1], &recipies[1]), 0.0);
+
+    // Exporting at most 30 heavy oil/min forces some of it to be cracked.
+    solver
+        .add_output_constraint(products[0].clone(), OutputConstraint::AtMost(30.0))
+        .unwrap();
+    let plan = solver.solve().unwrap();
+    assert_eq!(plan.machine_count(&machines[0], &recipies[0]), 1.0);
+    assert_eq!(plan.machine_count(&machines[1], &recipies[1]), 2.0);
+    assert_eq!(plan.net_rate_of(&products[0]), 0.0);
+    assert_eq!(plan.net_rate_of(&products[1]), 120.0);
+
+    solver
+        .add_output_constraint(products[0].clone(), OutputConstraint::Exactly(30.0))
+        .unwrap();
+    let plan = solver.solve().unwrap();
+    assert_eq!(plan.net_rate_of(&products[0]), 30.0);
+    assert_eq!(plan.net_rate_of(&products[1]), 210.0);
+}
+
+#[test]
+fn solver_errors() {
+    let machines = vec![Machine::new("Assembling machine 1".to_owned(), 0.5)];
+    let products = vec![
+        Product::new("Iron plate".to_owned()),
+        Product::new("Iron gear wheel".to_owned()),
+    ];
+    let recipies = vec![Recipe::new(
+        "Iron gear wheel".to_owned(),
+        0.5,
+        HashMap::from([(products[0].clone(), 2.0)]),
+        HashMap::from([(products[1].clone(), 1.0)]),
+    )];
+
+    let mut solver = Solver::new(Model::new(
+        recipies.clone(),
+        products.clone(),
+        machines.clone(),
+    ));
+    assert_eq!(
+        solver.add_production_constraint(Product::new("Copper plate".to_owned()), 60.0),
+        Err(SolverError::UnknownProduct("Copper plate".to_owned()))
+    );
+    assert!(matches!(
+        solver.add_supply_constraint(products[0].clone(), -1.0),
+        Err(SolverError::InvalidAmount { .. })
+    ));
+
+    // Nothing makes iron plates, so no gears can be made either.
+    solver
+        .add_production_constraint(products[1].clone(), 60.0)
+        .unwrap();
+    assert_eq!(solver.solve().unwrap_err(), SolverError::Infeasible);
+
+    let broken = vec![Recipe::new(
+        "Iron gear wheel".to_owned(),
+        0.0,
+        HashMap::new(),
+        HashMap::from([(products[1].clone(), 1.0)]),
+    )];
+    let solver = Solver::new(Model::new(broken, products, machines));
+    assert!(matches!(
+        solver.solve().unwrap_err(),
+        SolverError::InvalidModel(issues)
+            if matches!(issues.as_slice(), [Issue::InvalidProductionTime { .. }])
+    ));
+}
+
+#[test]
+fn validate_model() {
+    let machines = vec![
+        Machine::new("Assembling machine 1".to_owned(), 0.5),
+        Machine::new("Assembling machine 1".to_owned(), 0.0),
+    ];
+    let products = vec![
+        Product::new("Iron plate".to_owned()),
+        Product::new("Iron gear wheel".to_owned()),
+        Product::new("Wood".to_owned()),
+    ];
+    let recipies = vec![
+        Recipe::new(
+            "Iron gear wheel".to_owned(),
+            0.5,
+            HashMap::from([(products[0].clone(), -2.0)]),
+            HashMap::from([(products[1].clone(), 1.0)]),
+        ),
+        Recipe::new(
+            "Iron gear wheel".to_owned(),
+            0.5,
+            HashMap::from([(Product::new("Copper plate".to_owned()), 1.0)]),
+            HashMap::from([(products[1].clone(), 1.0)]),
+        ),
+        Recipe::new(
+            "Stone furnace".to_owned(),
+            0.5,
+            HashMap::new(),
+            HashMap::new(),
+        )
+        .with_category("smelting".to_owned()),
+    ];
+
+    let model = Model::new(recipies, products, machines);
+    let validation = model.validate();
+    assert_eq!(
+        validation.issues(),
+        [
+            Issue::DuplicateMachine("Assembling machine 1".to_owned()),
+            Issue::InvalidProductionRate {
+                machine: "Assembling machine 1".to_owned(),
+                production_rate: 0.0
+            },
+            Issue::InvalidAmount {
+                recipe: "Iron gear wheel".to_owned(),
+                product: "Iron plate".to_owned(),
+                amount: -2.0
+            },
+            Issue::DuplicateRecipe("Iron gear wheel".to_owned()),
+            Issue::UnknownProduct {
+                recipe: "Iron gear wheel".to_owned(),
+                product: "Copper plate".to_owned()
+            },
+            Issue::EmptyRecipe("Stone furnace".to_owned()),
+            Issue::UncraftableRecipe("Stone furnace".to_owned()),
+            Issue::UnusedProduct("Wood".to_owned()),
+        ]
+    );
+    assert!(!validation.is_valid());
+    assert_eq!(validation.warnings().count(), 3);
+
+    let solver = Solver::new(model);
+    assert!(matches!(
+        solver.solve().unwrap_err(),
+        SolverError::InvalidModel(issues) if issues.len() == 5
+    ));
+}
+
+#[test]
+fn diagnose_infeasible_models() {
+    let machines = vec![Machine::new("Assembling machine 1".to_owned(), 0.5)];
+    let products = vec![
+        Product::new("Iron plate".to_owned()),
+        Product::new("Iron gear wheel".to_owned()),
+        Product::new("Copper plate".to_owned()),
+        Product::new("Copper cable".to_owned()),
+    ];
+    let recipies = vec![
+        Recipe::new(
+            "Iron gear wheel".to_owned(),
+            0.5,
+            HashMap::from([(products[0].clone(), 2.0)]),
+            HashMap::from([(products[1].clone(), 1.0)]),
+        ),
+        Recipe::new(
+            "Copper cable".to_owned(),
+            0.5,
+            HashMap::from([(products[2].clone(), 1.0)]),
+            HashMap::from([(products[3].clone(), 2.0)]),
+        ),
+    ];
+
+    // Nothing makes copper plates, so no cable can be made either.
+    let mut solver = Solver::new(Model::new(
+        recipies.clone(),
+        products.clone(),
+        machines.clone(),
+    ));
+    solver
+        .add_production_constraint(products[3].clone(), 60.0)
+        .unwrap();
+    let diagnosis = solver.diagnose().unwrap();
+    assert_eq!(
+        diagnosis
+            .unproducible()
+            .iter()
+            .map(|p| (p.product(), p.reason().clone()))
+            .collect::<Vec<_>>(),
+        vec![
+            (
+                "Copper cable",
+                Reason::MissingInputs(vec!["Copper plate".to_owned()])
+            ),
+            ("Copper plate", Reason::NoRecipe),
+        ]
+    );
+
+    // 60 gears a minute need 120 plates, but only 60 are supplied.
+    let mut solver = Solver::new(Model::new(recipies, products.clone(), machines));
+    solver
+        .add_production_constraint(products[1].clone(), 60.0)
+        .unwrap();
+    solver
+        .add_supply_constraint(products[0].clone(), 60.0)
+        .unwrap();
+    let diagnosis = solver.diagnose().unwrap();
+    assert!(diagnosis.unproducible().is_empty());
+    assert_eq!(
+        diagnosis.conflict(),
+        [
+            ConflictingConstraint::Balance("Iron plate".to_owned()),
+            ConflictingConstraint::MinimumOutput {
+                product: "Iron gear wheel".to_owned(),
+                amount: 60.0
+            },
+            ConflictingConstraint::SupplyLimit {
+                product: "Iron plate".to_owned(),
+                amount: 60.0
+            },
+        ]
+    );
+
+    solver
+        .add_supply_constraint(products[0].clone(), 120.0)
+        .unwrap();
+    assert!(solver.diagnose().unwrap().is_feasible());
+}
+
+#[test]
+fn model_files_round_trip() {
+    let products = vec![
+        Product::new("Iron plate".to_owned()),
+        Product::new("Iron gear wheel".to_owned()),
+    ];
+    let speed = Module::new("Speed module".to_owned(), Effect::new(0.2, 0.0, 0.5, 0.0));
+    let machines = vec![Machine::new("Assembling machine 2".to_owned(), 0.75)
+        .with_crafting_categories(["crafting".to_owned(), "advanced-crafting".to_owned()])
+        .with_module_slots(2)
+        .with_loadout(Loadout::new(vec![speed], Vec::new()))
+        .with_power(150.0, 5.0)
+        .with_emissions(3.0)
+        .with_size(3, 3)];
+    let recipies = vec![Recipe::new(
+        "Iron gear wheel".to_owned(),
+        0.5,
+        HashMap::from([(products[0].clone(), 2.0)]),
+        HashMap::from([(products[1].clone(), 1.0)]),
+    )];
+    let model = Model::new(recipies, products, machines);
+
+    let json = model_file::write_model(&model).unwrap();
+    assert!(json.contains(r#""Iron plate": 2.0"#));
+    let parsed = model_file::parse_model(&json).unwrap();
+    assert_eq!(model_file::write_model(&parsed).unwrap(), json);
+
+    let path = temp_path("round-trip.json");
+    model_file::save_model(&model, &path).unwrap();
+    let loaded = model_file::load_model(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(model_file::write_model(&loaded).unwrap(), json);
+
+    let machine = &loaded.machines()[0];
+    assert_eq!(machine.crafting_speed(), 0.75 * 1.2);
+    assert_eq!(machine.active_power(), 150.0 * 1.5 + 5.0);
+    assert_eq!(machine.area(), 9.0);
+    assert_eq!(
+        loaded.recipies()[0].usage_of(&Product::new("Iron plate".to_owned())),
+        Some(2.0)
+    );
+
+    // TOML files hold the same model, picked by their extension.
+    let toml = model_file::write_model_as(&model, ModelFormat::Toml).unwrap();
+    assert!(toml.contains("[recipes.usage]\n\"Iron plate\" = 2.0\n"));
+    assert!(toml.contains("[[machines.loadout.modules]]"));
+    let path = temp_path("round-trip.toml");
+    model_file::save_model(&model, &path).unwrap();
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), toml);
+    let loaded = model_file::load_model(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(model_file::write_model(&loaded).unwrap(), json);
+}
+
+#[test]
+fn hand_written_model_file() {
+    let model = model_file::parse_model(
+        r#"{
+            "recipes": [
+                {
+                    "name": "Iron gear wheel",
+                    "production_time": 0.5,
+                    "usage": { "Iron plate": 2 },
+                    "production": { "Iron gear wheel": 1 }
+                },
+                {
+                    "name": "Iron plate",
+                    "category": "smelting",
+                    "production_time": 3.2,
+                    "production": { "Iron plate": 1 }
+                }
+            ],
+            "products": ["Iron plate", "Iron gear wheel"],
+            "machines": [
+                { "name": "Assembling machine 1", "production_rate": 0.5 },
+                {
+                    "name": "Stone furnace",
+                    "production_rate": 1,
+                    "crafting_categories": ["smelting"]
+                }
+            ]
+        }"#,
+    )
+    .unwrap();
+    assert!(model.validate().is_valid());
+
+    let json = model_file::write_model(&model).unwrap();
+    let mut solver = Solver::new(model);
+    solver
+        .add_production_constraint(Product::new("Iron gear wheel".to_owned()), 60.0)
+        .unwrap();
+    let plan = solver.solve().unwrap();
+    assert_eq!(plan.groups().len(), 2);
+    assert_eq!(plan.total_machines(), 1.0 + 7.0);
+
+    let toml = model_file::parse_model_as(
+        r#"
+        products = ["Iron plate", 'Iron gear wheel'] # Literal strings work too.
+
+        [[recipes]]
+        name = "Iron gear wheel"
+        production_time = 0.5
+        usage."Iron plate" = 2
+        production = { "Iron gear wheel" = 1 }
+
+        [[recipes]]
+        name = "Iron plate"
+        category = "smelting"
+        production_time = 3.2
+        [recipes.production]
+        "Iron plate" = 1
+
+        [[machines]]
+        name = "Assembling machine 1"
+        production_rate = 0.5
+
+        [[machines]]
+        name = "Stone furnace"
+        production_rate = 1
+        crafting_categories = [
+            "smelting",
+        ]
+        "#,
+        ModelFormat::Toml,
+    )
+    .unwrap();
+    assert_eq!(model_file::write_model(&toml).unwrap(), json);
+
+    let error = model_file::parse_model_as("products = [\"Iron plate\"\nname =", ModelFormat::Toml)
+        .unwrap_err();
+    assert!(error.to_string().contains("line 2"));
+    let twice = "products = []\n[recipes]\nname = \"a\"\n[recipes]\nname = \"b\"\n";
+    assert!(model_file::parse_model_as(twice, ModelFormat::Toml).is_err());
+}
+
+#[test]
+fn command_line_interface() {
+    let args = |args: &[&str]| cli::parse_args(args.iter().map(|a| a.to_string()));
+    assert_eq!(
+        args(&["solve", "model.json", "--target", "Iron gear wheel=2/s"]),
+        Ok(cli::Command::Solve {
+            model: "model.json".into(),
+            targets: vec![cli::Target::new("Iron gear wheel".to_owned(), 120.0)],
+            format: cli::PlanFormat::Text,
+            mode: SolveMode::Integer,
+        })
+    );
+    assert_eq!(
+        "Electronic circuit=120/min"
+            .parse::<cli::Target>()
+            .unwrap()
+            .rate(),
+        120.0
+    );
+    assert_eq!("Iron plate=60".parse::<cli::Target>().unwrap().rate(), 60.0);
+    assert!("Iron plate=60/day".parse::<cli::Target>().is_err());
+    assert!(args(&["solve", "model.json"]).is_err());
+    assert!(args(&["list-products", "model.json", "-t", "Iron plate=1"]).is_err());
+
+    let products = vec![
+        Product::new("Iron plate".to_owned()),
+        Product::new("Iron gear wheel".to_owned()),
+    ];
+    let machines = vec![Machine::new("Assembling machine 1".to_owned(), 0.5)];
+    let recipies = vec![
+        Recipe::new(
+            "Iron gear wheel".to_owned(),
+            0.5,
+            HashMap::from([(products[0].clone(), 2.0)]),
+            HashMap::from([(products[1].clone(), 1.0)]),
+        ),
+        Recipe::new(
+            "Iron plate".to_owned(),
+            0.5,
+            HashMap::new(),
+            HashMap::from([(products[0].clone(), 1.0)]),
+        ),
+    ];
+    let path = temp_path("cli.json");
+    model_file::save_model(&Model::new(recipies, products, machines), &path).unwrap();
+    let model = path.to_str().unwrap();
+
+    let run = |command: &[&str]| {
+        let mut out = Vec::new();
+        cli::run(&args(command).unwrap(), &mut out).map(|_| String::from_utf8(out).unwrap())
+    };
+    assert_eq!(
+        run(&["list-products", model]).unwrap(),
+        "Iron plate\nIron gear wheel\n"
+    );
+    assert_eq!(
+        run(&["list-recipes", model]).unwrap(),
+        "Iron gear wheel (crafting, 0.5s): 2 Iron plate -> 1 Iron gear wheel\n\
+         Iron plate (crafting, 0.5s): nothing -> 1 Iron plate\n"
+    );
+    assert_eq!(run(&["validate", model]).unwrap(), "The model is valid.\n");
+
+    let plan = run(&["solve", model, "--target=Iron gear wheel=60/min"]).unwrap();
+    assert!(plan.contains("  1 x Assembling machine 1 running Iron gear wheel\n"));
+    assert!(plan.contains("  2 x Assembling machine 1 running Iron plate\n"));
+    assert!(plan.contains("Output:\n  Iron gear wheel: 60/min\n"));
+
+    let graph = run(&["solve", model, "-t", "Iron gear wheel=60", "-f", "mermaid"]).unwrap();
+    assert!(graph.starts_with("flowchart LR\n"));
+
+    let error = run(&["solve", model, "--target=Copper plate=60/min"]).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        SolverError::UnknownProduct("Copper plate".to_owned()).to_string()
+    );
+    std::fs::remove_file(&path).unwrap();
+
+    // Beacons are listed after a machine's own modules, even without any.
+    let speed = Module::new("Speed module".to_owned(), Effect::new(0.5, 0.0, 0.5, 0.0));
+    let beacon = Beacon::new("Beacon".to_owned(), 0.5, 2);
+    let group = |loadout| {
+        let group = RecipeGroup::new(
+            "Assembling machine 1".to_owned(),
+            "Iron plate".to_owned(),
+            loadout,
+            1.0,
+            0.0,
+            0.0,
+            HashMap::new(),
+        );
+        let plan = ProductionPlan::new(
+            1.0,
+            vec![group],
+            HashMap::new(),
+            HashMap::new(),
+            HashMap::new(),
+        );
+        let mut out = Vec::new();
+        cli::write_plan(&plan, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    };
+    let beacons = vec![BeaconSetup::new(
+        beacon,
+        2,
+        vec![speed.clone(), speed.clone()],
+    )];
+    assert!(group(Loadout::new(Vec::new(), beacons.clone()))
+        .contains("running Iron plate with 2 x Beacon (Speed module, Speed module)\n"));
+    assert!(group(Loadout::new(vec![speed], beacons)).contains(
+        "running Iron plate with Speed module, 2 x Beacon (Speed module, Speed module)\n"
+    ));
+}
+
+#[test]
+fn export_plan_graphs() {
+    let products = vec![
+        Product::new("Iron plate".to_owned()),
+        Product::new("Iron gear wheel".to_owned()),
+    ];
+    let machines = vec![Machine::new("Assembling machine 1".to_owned(), 0.5)];
+    let recipies = vec![Recipe::new(
+        "Iron gear wheel".to_owned(),
+        0.5,
+        HashMap::from([(products[0].clone(), 2.0)]),
+        HashMap::from([(products[1].clone(), 1.0)]),
+    )];
+    let mut solver = Solver::new(Model::new(recipies, products.clone(), machines));
+    solver
+        .add_supply_constraint(products[0].clone(), 1000.0)
+        .unwrap();
+    solver
+        .add_production_constraint(products[1].clone(), 90.0)
+        .unwrap();
+    let plan = solver.solve().unwrap();
+    assert_eq!(plan.groups()[0].rate_of(&products[0]), -240.0);
+    assert_eq!(plan.groups()[0].rate_of(&products[1]), 120.0);
+
+    // Two machines make 120 gears a minute, but only 90 are needed, so the
+    // other 30 overflow along with the target.
+    assert_eq!(
+        graph::to_dot(&plan),
+        r#"digraph production {
+    rankdir=LR;
+    group0 [label="2 × Assembling machine 1\nIron gear wheel", shape=box];
+    product0 [label="Iron gear wheel (output)", shape=ellipse];
+    product1 [label="Iron plate (supplied)", shape=ellipse];
+    group0 -> product0 [label="120 Iron gear wheel/min"];
+    product1 -> group0 [label="240 Iron plate/min"];
+}
+"#
+    );
+    assert_eq!(
+        graph::to_mermaid(&plan),
+        r#"flowchart LR
+    group0["2 × Assembling machine 1<br/>Iron gear wheel"]
+    product0(["Iron gear wheel (output)"])
+    product1(["Iron plate (supplied)"])
+    group0 -->|"120 Iron gear wheel/min"| product0
+    product1 -->|"240 Iron plate/min"| group0
+"#
+    );
+}
+
+#[test]
+fn fluids_with_temperatures() {
+    let water = Product::fluid("Water".to_owned());
+    let low = Product::fluid("Steam".to_owned()).with_temperature(165.0);
+    let high = Product::fluid("Steam".to_owned()).with_temperature(500.0);
+    let electricity = Product::new("Electricity".to_owned());
+    let steam = Product::new("Steam".to_owned());
+    assert_ne!(low, high);
+    assert_eq!(high.key(), "Steam@500");
+
+    let machines = vec![
+        Machine::new("Boiler".to_owned(), 1.0).with_crafting_categories(["boiling".to_owned()]),
+        Machine::new("Generator".to_owned(), 1.0)
+            .with_crafting_categories(["generating".to_owned()]),
+    ];
+    let recipe = |name: &str, category: &str, input: &Product, output: &Product, amount| {
+        Recipe::new(
+            name.to_owned(),
+            1.0,
+            HashMap::from([(input.clone(), 1.0)]),
+            HashMap::from([(output.clone(), amount)]),
+        )
+        .with_category(category.to_owned())
+    };
+    let recipies = vec![
+        recipe("Boiler", "boiling", &water, &low, 1.0),
+        recipe("Heat exchanger", "boiling", &water, &high, 1.0),
+        recipe("Steam engine", "generating", &steam, &electricity, 1.0)
+            .with_temperature_range("Steam".to_owned(), TemperatureRange::new(None, Some(165.0))),
+        recipe("Steam turbine", "generating", &steam, &electricity, 5.0)
+            .with_temperature_range("Steam".to_owned(), TemperatureRange::new(Some(500.0), None)),
+    ];
+
+    // Without high temperature steam, no turbine can run, and the heat
+    // exchanger produces an unknown product.
+    let products = vec![water.clone(), low.clone(), electricity.clone()];
+    let model = Model::new(recipies.clone(), products, machines.clone());
+    let validation = model.validate();
+    assert_eq!(
+        validation.errors().collect::<Vec<_>>(),
+        [&Issue::UnknownProduct {
+            recipe: "Heat exchanger".to_owned(),
+            product: "Steam@500".to_owned()
+        }]
+    );
+    assert_eq!(
+        validation.warnings().collect::<Vec<_>>(),
+        [&Issue::NoFluidInRange {
+            recipe: "Steam turbine".to_owned(),
+            fluid: "Steam".to_owned()
+        }]
+    );
+    assert_eq!(model.recipe_variants().len(), 3);
+
+    let products = vec![
+        water.clone(),
+        low.clone(),
+        high.clone(),
+        electricity.clone(),
+    ];
+    let model = Model::new(recipies, products, machines);
+    assert!(model.validate().is_valid());
+    assert_eq!(
+        model
+            .recipe_variants()
+            .iter()
+            .find(|r| r.name() == "Steam turbine")
+            .unwrap()
+            .usage_of(&high),
+        Some(1.0)
+    );
+
+    let json = model_file::write_model(&model).unwrap();
+    assert!(json.contains(r#""Steam@500": 1.0"#));
+    let model = model_file::parse_model(&json).unwrap();
+    assert_eq!(model_file::write_model(&model).unwrap(), json);
+    assert!(model.recipies()[0].usage().keys().all(Product::is_fluid));
+
+    let mut solver = Solver::new(model);
+    solver.add_supply_constraint(water.clone(), 1000.0).unwrap();
+    solver
+        .add_production_constraint(electricity.clone(), 300.0)
+        .unwrap();
+    let plan = solver.solve().unwrap();
+    assert_eq!(plan.total_machines(), 2.0);
+    assert_eq!(plan.net_rate_of(&high), 0.0);
+    assert_eq!(plan.groups()[1].recipe(), "Steam turbine");
+    assert_eq!(plan.groups()[1].rate_of(&high), -60.0);
+    assert_eq!(plan.net_rate_of(&electricity), 300.0);
+
+    // Pipes carrying only 30 high temperature steam a minute can't keep a
+    // whole heat exchanger running, so five steam engines take over, and
+    // 200 water a minute isn't enough for their boilers.
+    solver.add_pipe_throughput(high.clone(), 30.0).unwrap();
+    let plan = solver.solve().unwrap();
+    assert_eq!(plan.total_machines(), 10.0);
+    assert_eq!(plan.net_rate_of(&electricity), 300.0);
+    solver.add_pipe_throughput(water.clone(), 200.0).unwrap();
+    assert_eq!(solver.solve().unwrap_err(), SolverError::Infeasible);
+    let diagnosis = solver.diagnose().unwrap();
+    assert!(diagnosis
+        .conflict()
+        .contains(&ConflictingConstraint::PipeThroughput {
+            product: "Water".to_owned(),
+            amount: 200.0
+        }));
+    assert!(matches!(
+        solver.add_pipe_throughput(water, -1.0),
+        Err(SolverError::InvalidAmount { .. })
+    ));
+}
+
+#[test]
+fn probabilistic_and_ranged_outputs() {
+    let json = r#"{
+        "recipe": {
+            "uranium-processing": {
+                "name": "uranium-processing",
+                "category": "centrifuging",
+                "energy_required": 12,
+                "ingredients": [["uranium-ore", 10]],
+                "results": [
+                    {"name": "uranium-235", "amount": 1, "probability": 0.007},
+                    {"name": "uranium-238", "amount": 1, "probability": 0.993}
+                ]
+            },
+            "sifting": {
+                "name": "sifting",
+                "ingredients": [["gravel", 1]],
+                "results": [{"name": "stone", "amount_min": 1, "amount_max": 3}]
+            }
+        },
+        "assembling-machine": {
+            "centrifuge": {
+                "name": "centrifuge",
+                "crafting_speed": 1,
+                "crafting_categories": ["centrifuging"],
+                "energy_usage": "350kW",
+                "energy_source": {"type": "electric"}
+            }
+        }
+    }"#;
+    let model = dump::parse_model(json, Difficulty::Normal).unwrap();
+    let u235 = Product::new("uranium-235".to_owned());
+    let u238 = Product::new("uranium-238".to_owned());
+    let processing = &model.recipies()[1];
+    assert_eq!(
+        processing.production()[&u235],
+        Output::new(1.0).with_probability(0.007)
+    );
+    assert_eq!(processing.production_of(&u235), Some(0.007));
+    let sifting = &model.recipies()[0];
+    let stone = Product::new("stone".to_owned());
+    assert_eq!(sifting.production()[&stone], Output::ranged(1.0, 3.0));
+    assert_eq!(sifting.production_of(&stone), Some(2.0));
+
+    let saved = model_file::write_model(&model).unwrap();
+    let value = serde_json::from_str::<serde_json::Value>(&saved).unwrap();
+    assert_eq!(
+        value["recipes"][1]["production"]["uranium-235"],
+        serde_json::json!({"amount": 1.0, "probability": 0.007})
+    );
+    assert_eq!(
+        value["recipes"][0]["production"]["stone"],
+        serde_json::json!({"amount_min": 1.0, "amount_max": 3.0})
+    );
+    let model = model_file::parse_model(&saved).unwrap();
+    assert_eq!(model_file::write_model(&model).unwrap(), saved);
+
+    // A centrifuge runs five crafts a minute, for 0.035 U-235 on average.
+    let mut solver = Solver::new(model);
+    solver
+        .add_supply_constraint(Product::new("uranium-ore".to_owned()), 1000.0)
+        .unwrap();
+    solver
+        .add_production_constraint(u235.clone(), 0.35)
+        .unwrap();
+    let plan = solver.solve().unwrap();
+    assert_eq!(plan.total_machines(), 10.0);
+    assert!((plan.net_rate_of(&u238) - 10.0 * 5.0 * 0.993).abs() < 1e-9);
+    assert!((plan.supply_of(&Product::new("uranium-ore".to_owned())) - 500.0).abs() < 1e-9);
+
+    let broken = Recipe::new(
+        "sifting".to_owned(),
+        0.5,
+        HashMap::from([(Product::new("gravel".to_owned()), 1.0)]),
+        HashMap::new(),
+    )
+    .with_output(stone, Output::ranged(3.0, 1.0).with_probability(1.5));
+    let products = vec![
+        Product::new("gravel".to_owned()),
+        Product::new("stone".to_owned()),
+    ];
+    let machines = vec![Machine::new("Assembling machine 1".to_owned(), 0.5)];
+    assert_eq!(
+        Model::new(vec![broken], products, machines)
+            .validate()
+            .issues(),
+        [Issue::InvalidOutput {
+            recipe: "sifting".to_owned(),
+            product: "stone".to_owned()
+        }]
+    );
+}
+
+#[test]
+fn productivity_skips_catalysts() {
+    let productivity = Module::new(
+        "Productivity module".to_owned(),
+        Effect::new(0.0, 0.2, 0.0, 0.0),
+    );
+    let loadout = Loadout::new(vec![productivity], Vec::new());
+
+    let json = r#"{
+        "recipe": {
+            "kovarex-enrichment-process": {
+                "name": "kovarex-enrichment-process",
+                "category": "centrifuging",
+                "energy_required": 60,
+                "ingredients": [["uranium-235", 40], ["uranium-238", 5]],
+                "results": [
+                    {"name": "uranium-235", "amount": 41, "catalyst_amount": 40},
+                    {"name": "uranium-238", "amount": 2, "catalyst_amount": 2}
+                ]
+            }
+        },
+        "assembling-machine": {
+            "centrifuge": {
+                "name": "centrifuge",
+                "crafting_speed": 1,
+                "crafting_categories": ["centrifuging"],
+                "module_specification": {"module_slots": 2}
+            }
+        }
+    }"#;
+    let model = dump::parse_model(json, Difficulty::Normal).unwrap();
+    let u235 = Product::new("uranium-235".to_owned());
+    let u238 = Product::new("uranium-238".to_owned());
+    let kovarex = &model.recipies()[0];
+    assert_eq!(kovarex.production_of(&u235), Some(41.0));
+    assert_eq!(kovarex.production_with(&u235, 1.2), Some(41.2));
+    assert_eq!(kovarex.production_with(&u238, 1.2), Some(2.0));
+
+    // Each centrifuge crafts once a minute, and its module only adds to the
+    // one uranium-235 gained per craft rather than all 41 returned.
+    let mut solver = Solver::new(model);
+    solver.add_loadout(loadout.clone());
+    solver.add_supply_constraint(u238.clone(), 1000.0).unwrap();
+    solver.add_production_constraint(u235.clone(), 2.4).unwrap();
+    let plan = solver.solve().unwrap();
+    assert_eq!(plan.groups().len(), 1);
+    assert_eq!(plan.groups()[0].loadout(), &loadout);
+    assert_eq!(plan.total_machines(), 2.0);
+    assert!((plan.net_rate_of(&u235) - 2.4).abs() < 1e-9);
+    assert_eq!(plan.net_rate_of(&u238), -6.0);
+
+    let coal = Product::new("Coal".to_owned());
+    let heavy = Product::fluid("Heavy oil".to_owned());
+    let light = Product::fluid("Light oil".to_owned());
+    let gas = Product::fluid("Petroleum gas".to_owned());
+    let steam = Product::fluid("Steam".to_owned());
+    let liquefaction = Recipe::new(
+        "Coal liquefaction".to_owned(),
+        5.0,
+        HashMap::from([(coal.clone(), 10.0), (heavy.clone(), 25.0), (steam, 50.0)]),
+        HashMap::from([(light.clone(), 20.0), (gas.clone(), 10.0)]),
+    )
+    .with_output(heavy.clone(), Output::new(90.0).with_catalyst_amount(25.0))
+    .with_category("oil-processing".to_owned());
+    let refinery = Machine::new("Oil refinery".to_owned(), 1.0)
+        .with_crafting_categories(["oil-processing".to_owned()])
+        .with_module_slots(3)
+        .with_loadout(loadout);
+    let products = vec![
+        coal,
+        heavy.clone(),
+        light.clone(),
+        gas.clone(),
+        Product::fluid("Steam".to_owned()),
+    ];
+    let model = Model::new(vec![liquefaction], products.clone(), vec![refinery]);
+    let mut solver = Solver::new(model);
+    for supplied in [&products[0], &products[4]] {
+        solver
+            .add_supply_constraint(supplied.clone(), 10000.0)
+            .unwrap();
+    }
+    solver
+        .add_production_constraint(heavy.clone(), 1.0)
+        .unwrap();
+    let plan = solver.solve().unwrap();
+
+    // Twelve crafts a minute, each returning 90 + 20% of the 65 heavy oil
+    // beyond the 25 put in, 24 light oil and 12 petroleum gas.
+    let group = &plan.groups()[0];
+    assert_eq!(group.count(), 1.0);
+    assert!((group.rate_of(&heavy) - 12.0 * (103.0 - 25.0)).abs() < 1e-9);
+    assert!((group.rate_of(&light) - 12.0 * 24.0).abs() < 1e-9);
+    assert!((group.rate_of(&gas) - 12.0 * 12.0).abs() < 1e-9);
+}
+
+#[test]
+fn byproduct_disposal_policies() {
+    let crude = Product::fluid("Crude oil".to_owned());
+    let heavy = Product::fluid("Heavy oil".to_owned());
+    let light = Product::fluid("Light oil".to_owned());
+    let gas = Product::fluid("Petroleum gas".to_owned());
+    let processing = Recipe::new(
+        "Advanced oil processing".to_owned(),
+        5.0,
+        HashMap::from([(crude.clone(), 100.0)]),
+        HashMap::from([
+            (heavy.clone(), 25.0),
+            (light.clone(), 45.0),
+            (gas.clone(), 55.0),
+        ]),
+    )
+    .with_category("oil-processing".to_owned());
+    let cracking = Recipe::new(
+        "Heavy oil cracking".to_owned(),
+        2.0,
+        HashMap::from([(heavy.clone(), 40.0)]),
+        HashMap::from([(light.clone(), 30.0)]),
+    )
+    .with_category("chemistry".to_owned());
+    let refinery = Machine::new("Oil refinery".to_owned(), 1.0)
+        .with_crafting_categories(["oil-processing".to_owned()]);
+    let plant = Machine::new("Chemical plant".to_owned(), 1.0)
+        .with_crafting_categories(["chemistry".to_owned()]);
+    let model = || {
+        Model::new(
+            vec![processing.clone(), cracking.clone()],
+            vec![crude.clone(), heavy.clone(), light.clone(), gas.clone()],
+            vec![refinery.clone(), plant.clone()],
+        )
+    };
+    // One refinery makes 660 petroleum gas a minute, along with 300 heavy oil,
+    // while a chemical plant cracks 1200 heavy oil a minute.
+    let solver = || {
+        let mut solver = Solver::new(model());
+        solver
+            .add_supply_constraint(crude.clone(), 10000.0)
+            .unwrap();
+        solver
+            .add_production_constraint(gas.clone(), 660.0)
+            .unwrap();
+        solver
+    };
+
+    let free = solver();
+    assert_eq!(free.disposal_policy(&heavy), DisposalPolicy::Free);
+    let plan = free.solve().unwrap();
+    assert_eq!(plan.total_machines(), 1.0);
+    assert!((plan.overflow_of(&heavy) - 300.0).abs() < 1e-6);
+
+    let mut forbidden = solver();
+    forbidden
+        .set_disposal_policy(heavy.clone(), DisposalPolicy::Forbidden)
+        .unwrap();
+    // Machines run flat out, so it takes four refineries to keep a chemical
+    // plant busy.
+    let plan = forbidden.solve().unwrap();
+    assert_eq!(plan.total_machines(), 5.0);
+    assert!(plan.overflow_of(&heavy).abs() < 1e-6);
+
+    // Surplus asked for by an output constraint isn't surplus at all.
+    forbidden
+        .add_production_constraint(heavy.clone(), 300.0)
+        .unwrap();
+    let plan = forbidden.solve().unwrap();
+    assert_eq!(plan.total_machines(), 1.0);
+
+    // Dumping 300 heavy oil costs more than cracking it at 0.02 a unit, but
+    // less at 0.001.
+    let mut expensive = solver();
+    expensive
+        .set_disposal_policy(heavy.clone(), DisposalPolicy::Cost(0.02))
+        .unwrap();
+    let plan = expensive.solve().unwrap();
+    assert_eq!(plan.total_machines(), 5.0);
+    assert!((plan.objective() - 5.0).abs() < 1e-6);
+
+    let mut cheap = solver();
+    cheap
+        .set_disposal_policy(heavy.clone(), DisposalPolicy::Cost(0.001))
+        .unwrap();
+    let plan = cheap.solve().unwrap();
+    assert_eq!(plan.total_machines(), 1.0);
+    assert!((plan.objective() - 1.3).abs() < 1e-6);
+
+    assert!(matches!(
+        cheap.set_disposal_policy(heavy.clone(), DisposalPolicy::Cost(-1.0)),
+        Err(SolverError::InvalidAmount { .. })
+    ));
+    assert!(matches!(
+        cheap.set_disposal_policy(Product::new("Water".to_owned()), DisposalPolicy::Forbidden),
+        Err(SolverError::UnknownProduct(_))
+    ));
+
+    // Nothing consumes light oil, so it can't be kept from leaving.
+    let mut stuck = solver();
+    stuck
+        .set_disposal_policy(light.clone(), DisposalPolicy::Forbidden)
+        .unwrap();
+    assert!(matches!(stuck.solve(), Err(SolverError::Infeasible)));
+    let diagnosis = stuck.diagnose().unwrap();
+    assert!(diagnosis
+        .conflict()
+        .contains(&ConflictingConstraint::DisposalForbidden(
+            "Light oil".to_owned()
+        )));
+}
+
+#[test]
+fn continuous_and_rounded_modes() {
+    // A circuit assembler makes 60 circuits a minute, which takes 180 cables,
+    // one and a half cable assemblers' worth.
+    let machines = vec![Machine::new("Assembling machine 1".to_owned(), 0.5).with_power(75.0, 2.5)];
+    let (mut solver, [_, _, cable, _], _) = circuit_solver(machines);
+    let count_of = |plan: &ProductionPlan, recipe: &str| {
+        plan.groups()
+            .iter()
+            .find(|g| g.recipe() == recipe)
+            .map_or(0.0, |g| g.count())
+    };
+
+    let plan = solver.solve().unwrap();
+    assert_eq!(count_of(&plan, "Copper cable"), 2.0);
+    assert_eq!(plan.total_machines(), 3.0);
+    assert!((plan.overflow_of(&cable) - 60.0).abs() < 1e-6);
+
+    solver.set_mode(SolveMode::Continuous);
+    let plan = solver.solve().unwrap();
+    assert!((count_of(&plan, "Copper cable") - 1.5).abs() < 1e-6);
+    assert!((plan.total_machines() - 2.5).abs() < 1e-6);
+    assert!(plan.overflow_of(&cable).abs() < 1e-6);
+
+    // Rounding up adds a cable assembler that's idle half the time, so no
+    // more cable is made than before, and it only draws its drain while idle.
+    solver.set_mode(SolveMode::RoundedUp);
+    let plan = solver.solve().unwrap();
+    assert_eq!(count_of(&plan, "Copper cable"), 2.0);
+    assert_eq!(count_of(&plan, "Electronic circuit"), 1.0);
+    assert_eq!(plan.total_machines(), 3.0);
+    assert!((plan.objective() - 3.0).abs() < 1e-6);
+    assert!((plan.power() - (77.5 * 2.5 + 2.5 * 0.5)).abs() < 1e-6);
+    solver.set_objective(Objective::Power);
+    let plan = solver.solve().unwrap();
+    assert!((plan.objective() - plan.power()).abs() < 1e-6);
+    solver.set_objective(Objective::Machines);
+    assert!(plan.overflow_of(&cable).abs() < 1e-6);
+    assert!((plan.groups()[0].rate_of(&cable) - 180.0).abs() < 1e-6);
+
+    assert_eq!("rounded".parse(), Ok(SolveMode::RoundedUp));
+    assert!("fractional".parse::<SolveMode>().is_err());
+    let args = [
+        "solve",
+        "model.json",
+        "-t",
+        "Electronic circuit=1/s",
+        "--mode=continuous",
+    ];
+    assert!(matches!(
+        cli::parse_args(args.iter().map(|a| a.to_string())),
+        Ok(cli::Command::Solve {
+            mode: SolveMode::Continuous,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn solver_backends() {
+    let products = vec![
+        Product::new("Iron ore".to_owned()),
+        Product::new("Iron plate".to_owned()),
+        Product::new("Iron gear wheel".to_owned()),
+    ];
+    let recipies = vec![
+        Recipe::new(
+            "Iron plate".to_owned(),
+            3.2,
+            HashMap::from([(products[0].clone(), 1.0)]),
+            HashMap::from([(products[1].clone(), 1.0)]),
+        ),
+        Recipe::new(
+            "Iron gear wheel".to_owned(),
+            0.5,
+            HashMap::from([(products[1].clone(), 2.0)]),
+            HashMap::from([(products[2].clone(), 1.0)]),
+        ),
+    ];
+    let machines = vec![Machine::new("Assembling machine".to_owned(), 0.75)];
+    let mut solver = Solver::new(Model::new(recipies, products.clone(), machines));
+    solver
+        .add_supply_constraint(products[0].clone(), 1000.0)
+        .unwrap();
+    solver
+        .add_production_constraint(products[2].clone(), 45.0)
+        .unwrap();
+
+    // Only backends that are both compiled in and installed can be tried.
+    let cbc = cfg!(feature = "lp-solvers") && on_path("cbc");
+    let mut backends = Vec::new();
+    for (backend, available) in [
+        (SolverBackend::Microlp, cfg!(feature = "microlp")),
+        (SolverBackend::Cbc, cbc),
+        (SolverBackend::Highs, cfg!(feature = "highs")),
+        (SolverBackend::CoinCbc, cfg!(feature = "coin_cbc")),
+    ] {
+        if available {
+            backends.push(backend);
+        } else if backend != SolverBackend::Cbc {
+            solver.set_backend(backend);
+            assert!(matches!(solver.solve(), Err(SolverError::Backend(_))));
+        }
+    }
+    let default = match cbc || !cfg!(feature = "microlp") {
+        true => SolverBackend::Cbc,
+        false => SolverBackend::Microlp,
+    };
+    assert_eq!(SolverBackend::default(), default);
+
+    // A gear assembler makes 90 gears a minute out of 180 plates, while a
+    // plate assembler makes 14.0625 plates a minute.
+    for backend in &backends {
+        solver.set_backend(*backend);
+        let plan = solver.solve().unwrap();
+        assert_eq!(plan.total_machines(), 1.0 + 13.0, "{:?}", backend);
+
+        solver.set_mode(SolveMode::Continuous);
+        let plan = solver.solve().unwrap();
+        assert!((plan.total_machines() - (0.5 + 90.0 / 14.0625)).abs() < 1e-3);
+        solver.set_mode(SolveMode::Integer);
+    }
+
+    // A model whose byproducts leave the relaxation far from whole machine
+    // counts, which a plain depth-first search gave up on.
+    let products = (0..6)
+        .map(|i| Product::new(format!("P{}", i)))
+        .collect::<Vec<_>>();
+    let recipe = |name: &str, time: f64, usage: &[(usize, f64)], production: &[(usize, f64)]| {
+        let amounts = |amounts: &[(usize, f64)]| {
+            amounts
+                .iter()
+                .map(|(i, amount)| (products[*i].clone(), *amount))
+                .collect::<HashMap<_, _>>()
+        };
+        Recipe::new(name.to_owned(), time, amounts(usage), amounts(production))
+    };
+    let recipies = vec![
+        recipe("R0", 0.3, &[(0, 5.0)], &[(1, 1.0)]),
+        recipe("R1", 3.87, &[(1, 5.0)], &[(2, 1.0)]),
+        recipe("R2", 6.93, &[(2, 5.0)], &[(3, 3.0)]),
+        recipe("R3", 5.57, &[(1, 4.0)], &[(4, 2.0)]),
+        recipe("R4", 4.38, &[(1, 2.0), (2, 5.0)], &[(3, 1.0), (5, 3.0)]),
+        recipe("R5", 0.47, &[(1, 1.0), (2, 5.0)], &[(4, 1.0), (5, 3.0)]),
+        recipe("R6", 1.83, &[(4, 5.0)], &[(1, 1.0), (5, 1.0)]),
+        recipe("R7", 5.06, &[(2, 5.0)], &[(5, 3.0)]),
+    ];
+    let machines = vec![
+        Machine::new("M0".to_owned(), 0.5),
+        Machine::new("M1".to_owned(), 0.75),
+        Machine::new("M2".to_owned(), 1.25),
+    ];
+    let mut solver = Solver::new(Model::new(recipies, products.clone(), machines));
+    solver
+        .add_supply_constraint(products[0].clone(), 100000.0)
+        .unwrap();
+    solver
+        .add_production_constraint(products[5].clone(), 109.0)
+        .unwrap();
+    solver
+        .add_production_constraint(products[3].clone(), 137.0)
+        .unwrap();
+    for backend in &backends {
+        solver.set_backend(*backend);
+        let plan = solver.solve().unwrap();
+        assert_eq!(plan.total_machines(), 33.0, "{:?}", backend);
+    }
+}
+
+#[test]
+fn sensitivity_after_continuous_solve() {
+    let machines = vec![
+        Machine::new("Assembling machine 1".to_owned(), 0.5),
+        Machine::new("Burner assembler".to_owned(), 0.25),
+    ];
+    let (mut solver, [iron, copper, cable, circuit], recipies) = circuit_solver(machines.clone());
+
+    // Prices are only worked out when asked for.
+    assert!(solver.solve().unwrap().sensitivity().is_none());
+    solver.set_mode(SolveMode::Continuous);
+    assert!(solver.solve().unwrap().sensitivity().is_none());
+
+    // One more circuit a minute takes a sixtieth of a circuit assembler and
+    // three hundred and twentieths of a cable assembler. Integer mode gives
+    // the same prices, since they're always worked out continuously.
+    solver.set_mode(SolveMode::Integer);
+    let plan = solver.solve_with_sensitivity().unwrap();
+    assert!((plan.total_machines() - 2.5).abs() < 1e-6);
+    let sensitivity = plan.sensitivity().unwrap();
+    let close = |a: Option<f64>, b: f64| a.is_some_and(|a| (a - b).abs() < 1e-6);
+    assert!(close(
+        sensitivity.minimum_output_price(&circuit),
+        1.0 / 60.0 + 3.0 / 120.0
+    ));
+    assert!(close(sensitivity.shadow_price(&cable), 1.0 / 120.0));
+    assert!(close(sensitivity.shadow_price(&iron), 0.0));
+    assert_eq!(sensitivity.maximum_output_price(&circuit), None);
+
+    // Burner assemblers do half the work for the same cost per machine.
+    assert!(close(
+        sensitivity.reduced_cost(&machines[0], &recipies[0]),
+        0.0
+    ));
+    assert!(close(
+        sensitivity.reduced_cost(&machines[1], &recipies[0]),
+        0.5
+    ));
+    assert!(close(
+        sensitivity.reduced_cost(&machines[1], &recipies[1]),
+        0.5
+    ));
+    assert_eq!(sensitivity.reduced_costs().len(), 4);
+
+    // With exactly the 90 copper plates a minute the cable takes, no more
+    // circuits can be made, but lowering the target still saves as much as
+    // before. The used-up supply has a price too, and the machines in the
+    // plan still cost nothing extra.
+    solver.add_supply_constraint(copper.clone(), 90.0).unwrap();
+    let plan = solver.solve_with_sensitivity().unwrap();
+    let sensitivity = plan.sensitivity().unwrap();
+    let price = sensitivity.minimum_output_price(&circuit).unwrap();
+    assert!(price >= 1.0 / 60.0 + 3.0 / 120.0 - 1e-6);
+    assert!(sensitivity.shadow_price(&copper).unwrap().is_finite());
+    assert!(close(sensitivity.shadow_price(&iron), 0.0));
+    assert!(close(
+        sensitivity.reduced_cost(&machines[0], &recipies[0]),
+        0.0
+    ));
+
+    // Prices survive a round trip through JSON.
+    let json = serde_json::to_string(&plan).unwrap();
+    let parsed = serde_json::from_str::<ProductionPlan>(&json).unwrap();
+    assert_eq!(parsed.sensitivity(), plan.sensitivity());
+
+    // Plates come from smelting ore, or far more slowly out of thin air.
+    // With half an ore a minute to spare, the plan is right next to where
+    // smelting runs out of ore, but one more plate a minute still only takes
+    // a sixtieth of a furnace, and a magic furnace making a tenth of what a
+    // smelting one does saves 0.1 of one.
+    let ore = Product::new("Iron ore".to_owned());
+    let plate = Product::new("Iron plate".to_owned());
+    let recipies = vec![
+        Recipe::new(
+            "Smelt".to_owned(),
+            1.0,
+            HashMap::from([(ore.clone(), 1.0)]),
+            HashMap::from([(plate.clone(), 1.0)]),
+        ),
+        Recipe::new(
+            "Magic".to_owned(),
+            1.0,
+            HashMap::new(),
+            HashMap::from([(plate.clone(), 0.1)]),
+        ),
+    ];
+    let furnace = Machine::new("Furnace".to_owned(), 1.0);
+    let model = Model::new(
+        recipies.clone(),
+        vec![ore.clone(), plate.clone()],
+        vec![furnace.clone()],
+    );
+    let mut solver = Solver::new(model);
+    solver.set_mode(SolveMode::Continuous);
+    solver.add_supply_constraint(ore.clone(), 60.5).unwrap();
+    solver
+        .add_production_constraint(plate.clone(), 60.0)
+        .unwrap();
+    let plan = solver.solve_with_sensitivity().unwrap();
+    let sensitivity = plan.sensitivity().unwrap();
+    assert!(close(sensitivity.minimum_output_price(&plate), 1.0 / 60.0));
+    assert!(close(sensitivity.reduced_cost(&furnace, &recipies[0]), 0.0));
+    assert!(close(sensitivity.reduced_cost(&furnace, &recipies[1]), 0.9));
+    assert!(close(sensitivity.shadow_price(&ore), 0.0));
+
+    // Right at the breakpoint, a plate a minute costs a sixtieth of a
+    // furnace less, or a sixth of one more, and the price is one of the two
+    // or in between.
+    solver.add_supply_constraint(ore.clone(), 60.0).unwrap();
+    let plan = solver.solve_with_sensitivity().unwrap();
+    let price = plan
+        .sensitivity()
+        .unwrap()
+        .minimum_output_price(&plate)
+        .unwrap();
+    assert!((1.0 / 60.0 - 1e-6..=1.0 / 6.0 + 1e-6).contains(&price));
+}
+
+#[test]
+fn pareto_frontier_between_objectives() {
+    let machines = vec![
+        Machine::new("Assembling machine 2".to_owned(), 0.75).with_power(150.0, 5.0),
+        Machine::new("Assembling machine 3".to_owned(), 1.25).with_power(375.0, 12.5),
+    ];
+    let products = vec![Product::new("Iron gear wheel".to_owned())];
+    let recipies = vec![Recipe::new(
+        "Iron gear wheel".to_owned(),
+        0.5,
+        HashMap::new(),
+        HashMap::from([(products[0].clone(), 1.0)]),
+    )];
+    let model = Model::new(recipies.clone(), products.clone(), machines.clone());
+    let path = temp_path("pareto.json");
+    model_file::save_model(&model, &path).unwrap();
+    let mut solver = Solver::new(model);
+    solver
+        .add_production_constraint(products[0].clone(), 150.0)
+        .unwrap();
+
+    // One fast assembler, or two slow ones drawing less power between them.
+    // Every bound in between lands on one of the two.
+    let frontier = solver
+        .pareto_frontier(&Objective::Machines, &Objective::Power, 5)
+        .unwrap();
+    let values = frontier
+        .iter()
+        .map(|p| (p.first(), p.second()))
+        .collect::<Vec<_>>();
+    assert_eq!(values, vec![(1.0, 387.5), (2.0, 310.0)]);
+    assert_eq!(
+        frontier[0].plan().machine_count(&machines[1], &recipies[0]),
+        1.0
+    );
+    assert_eq!(
+        frontier[1].plan().machine_count(&machines[0], &recipies[0]),
+        2.0
+    );
+
+    // With fractional machines, the frontier is a line between the two
+    // assemblers.
+    solver.set_mode(SolveMode::Continuous);
+    let frontier = solver
+        .pareto_frontier(&Objective::Machines, &Objective::Power, 3)
+        .unwrap();
+    assert_eq!(frontier.len(), 3);
+    let (machines_at, power_at) = (frontier[1].first(), frontier[1].second());
+    assert!((power_at - (387.5 + 155.0 * 150.0 / 90.0) / 2.0).abs() < 1e-3);
+    assert!((machines_at - (1.0 + 150.0 / 90.0) / 2.0).abs() < 1e-3);
+    for points in [0, 1] {
+        assert_eq!(
+            solver
+                .pareto_frontier(&Objective::Machines, &Objective::Power, points)
+                .unwrap_err(),
+            SolverError::TooFewPoints(points)
+        );
+    }
+
+    let args = [
+        "pareto",
+        path.to_str().unwrap(),
+        "-t",
+        "Iron gear wheel=150",
+        "--objectives=machines,power",
+    ];
+    let command = cli::parse_args(args.iter().map(|a| a.to_string())).unwrap();
+    let mut out = Vec::new();
+    cli::run(&command, &mut out).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "machines,power,machines\n\
+         1,387.5,1 x Assembling machine 3 running Iron gear wheel\n\
+         2,310,2 x Assembling machine 2 running Iron gear wheel\n"
+    );
+    std::fs::remove_file(&path).unwrap();
+
+    let args = ["pareto", "model.json", "-t", "Iron gear wheel=1"];
+    assert!(cli::parse_args(args.iter().map(|a| a.to_string())).is_err());
+    let args = ["solve", "model.json", "-t", "Iron gear wheel=1", "-n", "3"];
+    assert!(cli::parse_args(args.iter().map(|a| a.to_string())).is_err());
+}
+
+#[test]
+fn sweep_production_rate() {
+    let machines = vec![
+        Machine::new("Assembling machine 2".to_owned(), 0.75).with_power(150.0, 5.0),
+        Machine::new("Assembling machine 3".to_owned(), 1.25).with_power(375.0, 12.5),
+    ];
+    let products = vec![
+        Product::new("Iron plate".to_owned()),
+        Product::new("Iron gear wheel".to_owned()),
+    ];
+    let recipies = vec![Recipe::new(
+        "Iron gear wheel".to_owned(),
+        0.5,
+        HashMap::from([(products[0].clone(), 2.0)]),
+        HashMap::from([(products[1].clone(), 1.0)]),
+    )];
+    let model = Model::new(recipies.clone(), products.clone(), machines.clone());
+    let mut solver = Solver::new(model);
+    solver
+        .add_supply_constraint(products[0].clone(), 1000.0)
+        .unwrap();
+    solver
+        .add_production_constraint(products[1].clone(), 90.0)
+        .unwrap();
+    solver.set_objective(Objective::Lexicographic(vec![
+        Objective::Power,
+        Objective::Machines,
+    ]));
+
+    // Slow assemblers draw the least power for the gears they make, so they
+    // scale up one for every 90 gears per minute, each running flat out.
+    let sweep = solver
+        .sweep(&products[1], &[45.0, 180.0, 450.0])
+        .unwrap()
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    let rates = sweep.iter().map(|p| p.rate()).collect::<Vec<_>>();
+    assert_eq!(rates, vec![45.0, 180.0, 450.0]);
+    let counts = sweep
+        .iter()
+        .map(|p| p.plan().machine_count(&machines[0], &recipies[0]))
+        .collect::<Vec<_>>();
+    assert_eq!(counts, vec![1.0, 2.0, 5.0]);
+    let power = sweep.iter().map(|p| p.plan().power()).collect::<Vec<_>>();
+    assert_eq!(power, vec![155.0, 310.0, 775.0]);
+    let plates = sweep
+        .iter()
+        .map(|p| p.plan().supply()["Iron plate"])
+        .collect::<Vec<_>>();
+    assert_eq!(plates, vec![180.0, 360.0, 900.0]);
+    assert_eq!(sweep[2].plan().objective(), 5.0);
+
+    // Sweeping leaves the solver's own constraints alone.
+    let plan = solver.solve().unwrap();
+    assert_eq!(plan.machine_count(&machines[0], &recipies[0]), 1.0);
+
+    // A rate beyond the supply of plates fails on its own.
+    let sweep = solver.sweep(&products[1], &[90.0, 900.0, 180.0]).unwrap();
+    assert_eq!(sweep.len(), 3);
+    assert_eq!(sweep[0].as_ref().unwrap().plan().objective(), 1.0);
+    assert!(matches!(sweep[1], Err(SolverError::Infeasible)));
+    assert_eq!(sweep[2].as_ref().unwrap().plan().objective(), 2.0);
+
+    // Surplus gears are charged for above each swept rate rather than the
+    // solver's own minimum: one assembler overshoots 45 gears a minute by 45.
+    solver
+        .set_disposal_policy(products[1].clone(), DisposalPolicy::Cost(0.5))
+        .unwrap();
+    let objectives = solver
+        .sweep(&products[1], &[45.0, 180.0])
+        .unwrap()
+        .iter()
+        .map(|p| p.as_ref().unwrap().plan().objective())
+        .collect::<Vec<_>>();
+    assert_eq!(objectives, vec![1.0 + 0.5 * 45.0, 2.0]);
+    assert!(matches!(
+        solver.sweep(&Product::new("Copper plate".to_owned()), &[90.0]),
+        Err(SolverError::UnknownProduct(_))
+    ));
+    assert!(matches!(
+        solver.sweep(&products[1], &[f64::NAN]),
+        Err(SolverError::InvalidAmount { .. })
+    ));
+}
+
+#[test]
+fn science_per_minute_target() {
+    let assembler = Machine::new("Assembling machine 1".to_owned(), 1.0);
+    let products = vec![
+        Product::new("Iron plate".to_owned()),
+        Product::new("Copper plate".to_owned()),
+        Product::new("Automation science pack".to_owned()),
+        Product::new("logistic-science-pack".to_owned()),
+    ];
+    let recipies = vec![
+        Recipe::new(
+            "Automation science pack".to_owned(),
+            5.0,
+            HashMap::from([(products[0].clone(), 1.0), (products[1].clone(), 1.0)]),
+            HashMap::from([(products[2].clone(), 1.0)]),
+        ),
+        Recipe::new(
+            "logistic-science-pack".to_owned(),
+            6.0,
+            HashMap::from([(products[0].clone(), 2.0)]),
+            HashMap::from([(products[3].clone(), 1.0)]),
+        ),
+    ];
+    let new_solver = || {
+        let model = Model::new(recipies.clone(), products.clone(), vec![assembler.clone()]);
+        let mut solver = Solver::new(model);
+        for plate in &products[..2] {
+            solver.add_supply_constraint(plate.clone(), 1000.0).unwrap();
+        }
+        solver
+    };
+    let packs = vec![SciencePack::Automation, SciencePack::Logistic];
+
+    // Packs are found by their own name as well as their prototype's, and
+    // each one is made at the same rate.
+    let mut solver = new_solver();
+    solver
+        .add_science_target(&ScienceTarget::new(packs.clone(), 60.0))
+        .unwrap();
+    let plan = solver.solve().unwrap();
+    assert_eq!(plan.machine_count(&assembler, &recipies[0]), 5.0);
+    assert_eq!(plan.machine_count(&assembler, &recipies[1]), 6.0);
+    assert_eq!(plan.net_rates()["Automation science pack"], 60.0);
+    assert_eq!(plan.net_rates()["logistic-science-pack"], 60.0);
+
+    // A 30 second unit researched 50% faster takes each lab 20 seconds, so
+    // 60 SPM keeps 20 labs busy, and every pack goes into them.
+    let lab = Machine::new("Lab".to_owned(), 1.0).with_power(60.0, 0.0);
+    let target = ScienceTarget::new(packs, 60.0)
+        .with_labs(Labs::new(lab.clone(), 30.0).with_speed_bonus(0.5));
+    let mut solver = new_solver();
+    solver.add_science_target(&target).unwrap();
+    let plan = solver.solve().unwrap();
+    let research = Recipe::new(RESEARCH.to_owned(), 20.0, HashMap::new(), HashMap::new());
+    assert_eq!(plan.machine_count(&lab, &research), 20.0);
+    assert_eq!(plan.machine_count(&assembler, &recipies[0]), 5.0);
+    assert_eq!(plan.machine_count(&assembler, &recipies[1]), 6.0);
+    assert_eq!(plan.power(), 1200.0);
+    assert_eq!(plan.net_rates()["Automation science pack"], 0.0);
+    assert_eq!(plan.net_rates()[RESEARCH], 60.0);
+
+    // A later target replaces the labs of an earlier one, and without labs
+    // of its own leaves the packs as output again, with no research at all.
+    solver.add_science_target(&target).unwrap();
+    let plan = solver.solve().unwrap();
+    assert_eq!(plan.machine_count(&lab, &research), 20.0);
+    assert_eq!(plan.total_machines(), 31.0);
+    solver
+        .add_science_target(&ScienceTarget::new(vec![SciencePack::Automation], 30.0))
+        .unwrap();
+    let plan = solver.solve().unwrap();
+    assert_eq!(plan.machine_count(&lab, &research), 0.0);
+    assert_eq!(plan.total_machines(), 3.0);
+    assert_eq!(plan.net_rates()["Automation science pack"], 36.0);
+    assert!(!plan.net_rates().contains_key(RESEARCH));
+
+    // Labs taking over from a target without them drop the minimums it set
+    // on the packs, keeping the ones set before it.
+    let mut solver = new_solver();
+    solver
+        .add_production_constraint(products[2].clone(), 12.0)
+        .unwrap();
+    let automation = ScienceTarget::new(vec![SciencePack::Automation], 60.0);
+    solver.add_science_target(&automation).unwrap();
+    solver
+        .add_science_target(&automation.clone().with_labs(Labs::new(lab.clone(), 20.0)))
+        .unwrap();
+    let plan = solver.solve().unwrap();
+    assert_eq!(plan.machine_count(&lab, &research), 20.0);
+    assert_eq!(plan.machine_count(&assembler, &recipies[0]), 6.0);
+    assert_eq!(plan.net_rates()["Automation science pack"], 12.0);
+    solver
+        .add_science_target(&ScienceTarget::new(vec![SciencePack::Logistic], 10.0))
+        .unwrap();
+    let plan = solver.solve().unwrap();
+    assert_eq!(plan.total_machines(), 1.0 + 1.0);
+    assert_eq!(plan.net_rates()["Automation science pack"], 12.0);
+
+    // Nor can the research recipe take the name of a recipe of the model.
+    let mut clashing = recipies.clone();
+    clashing.push(Recipe::new(
+        RESEARCH.to_owned(),
+        1.0,
+        HashMap::new(),
+        HashMap::from([(products[0].clone(), 1.0)]),
+    ));
+    let model = Model::new(clashing, products.clone(), vec![assembler.clone()]);
+    assert_eq!(
+        Solver::new(model)
+            .add_science_target(&automation.clone().with_labs(Labs::new(lab.clone(), 20.0))),
+        Err(SolverError::InvalidModel(vec![Issue::DuplicateRecipe(
+            RESEARCH.to_owned()
+        )]))
+    );
+
+    // Labs can't take the name of another machine, nor research in no time.
+    let clashing = Labs::new(assembler.clone(), 30.0);
+    assert_eq!(
+        solver.add_science_target(
+            &ScienceTarget::new(vec![SciencePack::Automation], 60.0).with_labs(clashing)
+        ),
+        Err(SolverError::InvalidModel(vec![Issue::DuplicateMachine(
+            "Assembling machine 1".to_owned()
+        )]))
+    );
+    for labs in [
+        Labs::new(lab.clone(), 0.0),
+        Labs::new(lab.clone(), f64::INFINITY),
+        Labs::new(lab.clone(), 30.0).with_speed_bonus(-1.0),
+    ] {
+        let target = ScienceTarget::new(vec![SciencePack::Automation], 60.0).with_labs(labs);
+        assert!(matches!(
+            solver.add_science_target(&target),
+            Err(SolverError::InvalidModel(issues))
+                if matches!(issues[..], [Issue::InvalidProductionTime { .. }])
+        ));
+    }
 
-    assert_eq!(solver.solve().unwrap(), 1.0);
+    assert!(matches!(
+        solver.add_science_target(&ScienceTarget::new(vec![SciencePack::Space], 60.0)),
+        Err(SolverError::UnknownProduct(p)) if p == "Space science pack"
+    ));
+    assert!(matches!(
+        solver.add_science_target(&ScienceTarget::new(vec![SciencePack::Automation], f64::NAN)),
+        Err(SolverError::InvalidAmount { .. })
+    ));
 }